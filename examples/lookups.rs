@@ -0,0 +1,40 @@
+/*lookup latency by degree and size on the fixture key sets
+ * cargo run --release --example lookups --features fixtures
+ * an unsafe get_unchecked without the bounds and empty map checks measured within noise
+ * of get here (the comparisons and cache misses dominate), so there is none
+ */
+use btreemap::{fixtures, BTreeMap};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+/*best of a few rounds of looking up every key once, per lookup*/
+fn time(map: &BTreeMap<u64, u64>, keys: &[u64]) -> Duration {
+    (0..5)
+        .map(|_| {
+            let started = Instant::now();
+            for &key in keys {
+                black_box(map.get(black_box(&key)));
+            }
+            started.elapsed() / keys.len() as u32
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    for min_degree in [2, 6, 32] {
+        for size in fixtures::SIZES {
+            let keys = fixtures::random(size, 1);
+            let mut map = BTreeMap::new(min_degree);
+            for &key in &keys {
+                map.insert(key, key);
+            }
+            println!(
+                "min_degree {:>2}, {:>7} keys: get {:>6.1?}",
+                min_degree,
+                size,
+                time(&map, &keys)
+            );
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{BTreeMap, SplitStrategy};
+
+/*inserts of one kind in a row it takes to switch to that mode*/
+const SWITCH_AFTER: usize = 16;
+
+/*what the last inserts of an adaptive map looked like
+ * a key past the largest one is ascending, one below the smallest descending, anything
+ * else (including a key that is already present) random
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InsertMode {
+    /*middle splits*/
+    #[default]
+    Random,
+    /*right biased splits and the right edge fast path of time series maps*/
+    Ascending,
+    /*left biased splits*/
+    Descending,
+}
+
+impl InsertMode {
+    fn split_strategy(self) -> SplitStrategy {
+        match self {
+            InsertMode::Random => SplitStrategy::Middle,
+            InsertMode::Ascending => SplitStrategy::RightBiased,
+            InsertMode::Descending => SplitStrategy::LeftBiased,
+        }
+    }
+}
+
+/*what the adaptive map is doing, to check it picked the right mode for a workload
+ * `switches` counts the mode changes, `fast_path_inserts` the inserts that went straight
+ * down the right edge
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct InsertStats {
+    pub mode: InsertMode,
+    pub switches: u64,
+    pub fast_path_inserts: u64,
+}
+
+/*the mode only changes after SWITCH_AFTER inserts of another kind in a row, so a stray
+ * key in a sequential stream doesn't throw away the packed spine
+ */
+#[derive(Debug, Clone, Default)]
+pub(crate) struct InsertPattern {
+    stats: InsertStats,
+    streak_mode: InsertMode,
+    streak: usize,
+}
+
+impl InsertPattern {
+    /*count one insert of `kind`, returns the mode to switch to once the streak is long enough*/
+    fn observe(&mut self, kind: InsertMode) -> Option<InsertMode> {
+        if kind == self.streak_mode {
+            self.streak += 1;
+        } else {
+            self.streak_mode = kind;
+            self.streak = 1;
+        }
+
+        if self.streak < SWITCH_AFTER || kind == self.stats.mode {
+            return None;
+        }
+        self.stats.mode = kind;
+        self.stats.switches += 1;
+        Some(kind)
+    }
+}
+
+impl<K: Ord, V> BTreeMap<K, V> {
+    /*let insert pick the split strategy and the right edge fast path from the keys it sees,
+     * see InsertMode, a map starts out in Random mode with middle splits
+     * turning it off keeps the strategy of the current mode, set_split_strategy turns it off
+     */
+    pub fn set_adaptive_inserts(&mut self, enabled: bool) {
+        if !enabled {
+            self.adaptive = None;
+            return;
+        }
+        if self.adaptive.is_none() {
+            self.apply_split_strategy(SplitStrategy::Middle);
+            self.adaptive = Some(InsertPattern::default());
+        }
+    }
+
+    /*None unless adaptive inserts are on*/
+    pub fn insert_stats(&self) -> Option<InsertStats> {
+        self.adaptive.as_ref().map(|pattern| pattern.stats)
+    }
+
+    /*classify `key` before it's inserted and switch modes once the pattern changed,
+     * returns whether the key should take the right edge fast path
+     */
+    pub(crate) fn adapt_to(&mut self, key: &K) -> bool {
+        if self.adaptive.is_none() {
+            return false;
+        }
+        let kind = match self.last_key() {
+            None => None,
+            Some(last) if key > last => Some(InsertMode::Ascending),
+            _ if self.first_key().is_some_and(|first| key < first) => Some(InsertMode::Descending),
+            _ => Some(InsertMode::Random),
+        };
+
+        let pattern = self.adaptive.as_mut().unwrap();
+        if let Some(mode) = kind.and_then(|kind| pattern.observe(kind)) {
+            self.apply_split_strategy(mode.split_strategy());
+        }
+
+        /*an empty map has no edge to be past, it follows the current mode*/
+        let pattern = self.adaptive.as_mut().unwrap();
+        let fast_path = pattern.stats.mode == InsertMode::Ascending
+            && kind.is_none_or(|kind| kind == InsertMode::Ascending);
+        if fast_path {
+            pattern.stats.fast_path_inserts += 1;
+        }
+        fast_path
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{BTreeMap, Codec};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/*archives start with the magic and the format version, see the README for the layout*/
+const ARCHIVE_MAGIC: &[u8; 4] = b"BTAR";
+const ARCHIVE_VERSION: u8 = 1;
+
+/*FNV-1a, enough to notice a truncated or corrupted copy*/
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/*passes bytes through while hashing them*/
+struct Checksummed<T> {
+    inner: T,
+    hash: u64,
+}
+
+impl<T> Checksummed<T> {
+    fn new(inner: T) -> Self {
+        Checksummed {
+            inner,
+            hash: FNV_OFFSET,
+        }
+    }
+
+    fn update(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.hash = (self.hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+impl<W: Write> Write for Checksummed<W> {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(bytes)?;
+        self.update(&bytes[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<R: Read> Read for Checksummed<R> {
+    fn read(&mut self, bytes: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(bytes)?;
+        self.update(&bytes[..read]);
+        Ok(read)
+    }
+}
+
+impl<K: Ord + Codec, V: Codec> BTreeMap<K, V> {
+    /*write every entry in key order plus the settings needed to rebuild the map
+     * everything is encoded with Codec, so the file reads back the same on any platform
+     */
+    pub fn export_archive<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut writer = Checksummed::new(BufWriter::new(File::create(path)?));
+        writer.write_all(ARCHIVE_MAGIC)?;
+        ARCHIVE_VERSION.encode(&mut writer)?;
+        (self.min_degree as u32).encode(&mut writer)?;
+        self.append_optimized.encode(&mut writer)?;
+        (self.len as u64).encode(&mut writer)?;
+        for (key, value) in self.iter() {
+            key.encode(&mut writer)?;
+            value.encode(&mut writer)?;
+        }
+
+        let hash = writer.hash;
+        hash.encode(&mut writer.inner)?;
+        writer.inner.into_inner()?.sync_all()
+    }
+
+    /*rebuild a map written by export_archive, packed bottom up like from_sorted_vec
+     * a merge operator is a function pointer and can't be archived, set it again after import,
+     * the same goes for the split strategy
+     */
+    pub fn import_archive<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let mut reader = Checksummed::new(BufReader::new(File::open(path)?));
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != ARCHIVE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an archive"));
+        }
+        let version = u8::decode(&mut reader)?;
+        if version != ARCHIVE_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported archive version {}", version),
+            ));
+        }
+
+        let min_degree = u32::decode(&mut reader)? as usize;
+        if min_degree < 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "archive min_degree below 2",
+            ));
+        }
+        let append_optimized = bool::decode(&mut reader)?;
+        let len = u64::decode(&mut reader)?;
+
+        /*no capacity from the header, a corrupted count must not allocate before it's checked*/
+        let mut entries: Vec<(K, V)> = Vec::new();
+        for _ in 0..len {
+            let key = K::decode(&mut reader)?;
+            if entries.last().is_some_and(|(last, _)| *last >= key) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "archive keys out of order",
+                ));
+            }
+            entries.push((key, V::decode(&mut reader)?));
+        }
+
+        let hash = reader.hash;
+        if u64::decode(&mut reader.inner)? != hash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "archive checksum mismatch",
+            ));
+        }
+        if reader.inner.read(&mut [0])? != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "trailing bytes after the archive checksum",
+            ));
+        }
+
+        let mut map = Self::from_sorted_vec(min_degree, entries);
+        map.append_optimized = append_optimized;
+        Ok(map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(name: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("btreemap-{}-{}", name, std::process::id()));
+        let mut map = BTreeMap::new(2);
+        for key in 0..50u32 {
+            map.insert(key, key.to_string());
+        }
+        map.export_archive(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn archive_round_trips() {
+        let path = export("round-trip");
+        let map = BTreeMap::<u32, String>::import_archive(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(map.len(), 50);
+        assert_eq!(map.get(&7).map(String::as_str), Some("7"));
+    }
+
+    #[test]
+    fn trailing_bytes_after_the_checksum_are_rejected() {
+        let path = export("trailing");
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.push(0);
+        std::fs::write(&path, &bytes).unwrap();
+
+        let error = BTreeMap::<u32, String>::import_archive(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
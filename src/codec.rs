@@ -0,0 +1,317 @@
+use std::io::{self, Read, Write};
+
+/*portable encoding for recorded keys and values
+ * integers are fixed width little endian, strings and byte vectors are a u32
+ * length followed by the bytes, so logs read back the same on every platform
+ */
+pub trait Codec: Sized {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! int_codec {
+    ($($int:ty),*) => {$(
+        impl Codec for $int {
+            fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.to_le_bytes())
+            }
+
+            fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+                let mut bytes = [0; std::mem::size_of::<$int>()];
+                reader.read_exact(&mut bytes)?;
+                Ok(<$int>::from_le_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+int_codec!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl Codec for bool {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        u8::from(*self).encode(writer)
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid bool")),
+        }
+    }
+}
+
+impl Codec for Vec<u8> {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let len = u32::try_from(self.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "entry longer than 4GiB"))?;
+        len.encode(writer)?;
+        writer.write_all(self)
+    }
+
+    /*the buffer grows with the bytes actually read, a corrupt length can't allocate 4GiB*/
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len = u32::decode(reader)? as usize;
+        let mut bytes = Vec::new();
+        reader.take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() != len {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "entry shorter than its length",
+            ));
+        }
+        Ok(bytes)
+    }
+}
+
+impl Codec for String {
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let len = u32::try_from(self.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "entry longer than 4GiB"))?;
+        len.encode(writer)?;
+        writer.write_all(self.as_bytes())
+    }
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+        String::from_utf8(Vec::decode(reader)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/*encoding for keys of the persistent engine: comparing two encoded keys byte by byte
+ * orders them the same way as comparing the keys themselves
+ * integers are fixed width big endian with the sign bit flipped, so negative numbers
+ * sort first, strings and byte vectors escape 0x00 as 0x00 0xFF and end with 0x00 0x00,
+ * which keeps a prefix before its extensions and lets tuples stack their fields
+ */
+pub trait KeyCodec: Sized {
+    fn encode_key<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    fn decode_key<R: Read>(reader: &mut R) -> io::Result<Self>;
+
+    fn to_key_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        self.encode_key(&mut bytes)
+            .expect("writing to a Vec never fails");
+        bytes
+    }
+}
+
+macro_rules! unsigned_key_codec {
+    ($($int:ty),*) => {$(
+        impl KeyCodec for $int {
+            fn encode_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                writer.write_all(&self.to_be_bytes())
+            }
+
+            fn decode_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+                let mut bytes = [0; std::mem::size_of::<$int>()];
+                reader.read_exact(&mut bytes)?;
+                Ok(<$int>::from_be_bytes(bytes))
+            }
+        }
+    )*};
+}
+
+unsigned_key_codec!(u8, u16, u32, u64, u128);
+
+macro_rules! signed_key_codec {
+    ($($int:ty => $unsigned:ty),*) => {$(
+        impl KeyCodec for $int {
+            fn encode_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                ((*self as $unsigned) ^ (1 << (<$unsigned>::BITS - 1))).encode_key(writer)
+            }
+
+            fn decode_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+                Ok((<$unsigned>::decode_key(reader)? ^ (1 << (<$unsigned>::BITS - 1))) as $int)
+            }
+        }
+    )*};
+}
+
+signed_key_codec!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+impl KeyCodec for bool {
+    fn encode_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        u8::from(*self).encode_key(writer)
+    }
+
+    fn decode_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::decode_key(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "invalid bool")),
+        }
+    }
+}
+
+fn encode_escaped<W: Write>(bytes: &[u8], writer: &mut W) -> io::Result<()> {
+    for (i, run) in bytes.split(|&byte| byte == 0).enumerate() {
+        if i > 0 {
+            writer.write_all(&[0, 0xFF])?;
+        }
+        writer.write_all(run)?;
+    }
+    writer.write_all(&[0, 0])
+}
+
+fn decode_escaped<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    loop {
+        match u8::decode_key(reader)? {
+            0 => match u8::decode_key(reader)? {
+                0 => return Ok(bytes),
+                0xFF => bytes.push(0),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "invalid escape in key",
+                    ))
+                }
+            },
+            byte => bytes.push(byte),
+        }
+    }
+}
+
+impl KeyCodec for Vec<u8> {
+    fn encode_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode_escaped(self, writer)
+    }
+
+    fn decode_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+        decode_escaped(reader)
+    }
+}
+
+impl KeyCodec for String {
+    fn encode_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        encode_escaped(self.as_bytes(), writer)
+    }
+
+    fn decode_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+        String::from_utf8(decode_escaped(reader)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+}
+
+/*fixed size keys such as UUIDs ([u8; 16]) or hashes go out as is*/
+impl<const N: usize> KeyCodec for [u8; N] {
+    fn encode_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self)
+    }
+
+    fn decode_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut bytes = [0; N];
+        reader.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/*fields one after the other, every field encoding is self delimiting*/
+macro_rules! tuple_key_codec {
+    ($(($($field:ident),+)),*) => {$(
+        impl<$($field: KeyCodec),+> KeyCodec for ($($field,)+) {
+            #[allow(non_snake_case)]
+            fn encode_key<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                let ($($field,)+) = self;
+                $($field.encode_key(writer)?;)+
+                Ok(())
+            }
+
+            fn decode_key<R: Read>(reader: &mut R) -> io::Result<Self> {
+                Ok(($($field::decode_key(reader)?,)+))
+            }
+        }
+    )*};
+}
+
+tuple_key_codec!((A, B), (A, B, C), (A, B, C, D));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn length_past_the_end_is_an_error_not_an_allocation() {
+        let mut bytes = Vec::new();
+        u32::MAX.encode(&mut bytes).unwrap();
+        bytes.extend_from_slice(b"short");
+
+        let error = Vec::<u8>::decode(&mut &bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        let error = String::decode(&mut &bytes[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn strings_and_bytes_round_trip() {
+        let mut bytes = Vec::new();
+        "héllo".to_string().encode(&mut bytes).unwrap();
+        vec![0u8, 1, 255].encode(&mut bytes).unwrap();
+
+        let mut reader = &bytes[..];
+        assert_eq!(String::decode(&mut reader).unwrap(), "héllo");
+        assert_eq!(Vec::<u8>::decode(&mut reader).unwrap(), vec![0, 1, 255]);
+        assert!(reader.is_empty());
+    }
+
+    /*every pair of samples, the bytes must sort like the keys and decode back*/
+    fn assert_order_preserved<K: KeyCodec + Ord + std::fmt::Debug>(samples: &[K]) {
+        for a in samples {
+            let a_bytes = a.to_key_bytes();
+            assert_eq!(&K::decode_key(&mut &a_bytes[..]).unwrap(), a);
+            for b in samples {
+                assert_eq!(a_bytes.cmp(&b.to_key_bytes()), a.cmp(b), "{a:?} vs {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn key_bytes_sort_like_the_keys() {
+        assert_order_preserved(&[i64::MIN, i64::MIN + 1, -256, -1, 0, 1, 255, i64::MAX]);
+        assert_order_preserved(&[i32::MIN, -70_000, -1, 0, 1, 70_000, i32::MAX]);
+        assert_order_preserved(&[0u64, 1, 255, 256, u64::MAX / 2, u64::MAX - 1, u64::MAX]);
+        assert_order_preserved(&[false, true]);
+        assert_order_preserved(&[
+            String::new(),
+            "\0".to_string(),
+            "\0\0".to_string(),
+            "\0a".to_string(),
+            "a".to_string(),
+            "a\0".to_string(),
+            "a\0b".to_string(),
+            "a\u{1}".to_string(),
+            "ab".to_string(),
+            "abc".to_string(),
+            "b".to_string(),
+            "é".to_string(),
+        ]);
+        assert_order_preserved(&[
+            vec![],
+            vec![0u8],
+            vec![0, 0],
+            vec![0, 0xFF],
+            vec![1],
+            vec![1, 0],
+            vec![0xFF],
+            vec![0xFF, 0],
+        ]);
+        assert_order_preserved(&[
+            (-1i32, String::new()),
+            (-1, "a".to_string()),
+            (0, String::new()),
+            (0, "\0".to_string()),
+            (0, "a".to_string()),
+            (0, "a\0".to_string()),
+            (0, "ab".to_string()),
+            (1, String::new()),
+        ]);
+        assert_order_preserved(&[
+            ("a".to_string(), i64::MIN, true),
+            ("a".to_string(), -1, false),
+            ("a".to_string(), -1, true),
+            ("a\0".to_string(), i64::MIN, false),
+            ("ab".to_string(), 0, false),
+        ]);
+    }
+}
@@ -0,0 +1,71 @@
+use crate::BTreeMap;
+use std::cell::Cell;
+use std::cmp::Ordering;
+
+thread_local! {
+    static COMPARISONS: Cell<u64> = const { Cell::new(0) };
+}
+
+/*key wrapper for tuning: every comparison between two wrapped keys bumps a counter of the
+ * current thread, so the work of an operation can be measured with count_comparisons
+ * independent of the machine, e.g. to compare degrees for a key type
+ * lookups need a wrapped key too, comparisons through Borrow would not be counted
+ */
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Counted<K>(pub K);
+
+impl<K: PartialEq> PartialEq for Counted<K> {
+    fn eq(&self, other: &Self) -> bool {
+        COMPARISONS.with(|count| count.set(count.get() + 1));
+        self.0 == other.0
+    }
+}
+
+impl<K: Eq> Eq for Counted<K> {}
+
+impl<K: Ord> PartialOrd for Counted<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: Ord> Ord for Counted<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        COMPARISONS.with(|count| count.set(count.get() + 1));
+        self.0.cmp(&other.0)
+    }
+}
+
+/*run `f` and return its result with the number of Counted comparisons it made on this thread*/
+pub fn count_comparisons<R>(f: impl FnOnce() -> R) -> (R, u64) {
+    let before = COMPARISONS.with(Cell::get);
+    let result = f();
+    (result, COMPARISONS.with(Cell::get) - before)
+}
+
+impl<K: Ord, V> BTreeMap<Counted<K>, V> {
+    /*comparisons a lookup of `key` makes in each node on its way down, root first
+     * it walks the tree exactly as get does, so the sum is what get costs
+     */
+    pub fn comparisons_per_level(&self, key: &Counted<K>) -> Vec<u64> {
+        let mut levels = Vec::new();
+        let mut node = self.root.as_ref();
+        while let Some(current) = node {
+            let (next, count) = count_comparisons(|| {
+                let pos = current
+                    .keys
+                    .iter()
+                    .position(|k| k >= key)
+                    .unwrap_or(current.keys.len());
+                if pos < current.keys.len() && current.keys[pos] == *key {
+                    None
+                } else {
+                    current.children.get(pos)
+                }
+            });
+            levels.push(count);
+            node = next;
+        }
+        levels
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{BTreeMap, KeyCodec, Range};
+use std::fmt;
+use std::io;
+use std::ops::Bound;
+use std::str::FromStr;
+
+/*tokens start with the format version, the rest is the last key seen, see KeyCodec*/
+const TOKEN_VERSION: u8 = 1;
+
+/*where a paginated scan left off, a scan resumes after the key it was made from even if
+ * that key was removed in the meantime, so no iterator has to be kept between calls
+ * it's just bytes: send them as they are, or as the hex string of Display and FromStr in
+ * URLs and JSON, clients should treat them as opaque
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResumeToken(Vec<u8>);
+
+impl ResumeToken {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /*the version is checked here, the key only when the token is used*/
+    pub fn from_bytes(bytes: Vec<u8>) -> io::Result<Self> {
+        match bytes.first() {
+            Some(&TOKEN_VERSION) => Ok(ResumeToken(bytes)),
+            Some(version) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported resume token version {}", version),
+            )),
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "empty resume token",
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ResumeToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ResumeToken {
+    type Err = io::Error;
+
+    fn from_str(hex: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "resume token is not hex");
+        let bytes = hex
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .filter(|pair| pair.len() == 2 && pair.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(invalid)
+            })
+            .collect::<io::Result<Vec<u8>>>()?;
+        Self::from_bytes(bytes)
+    }
+}
+
+impl<K: Ord + Clone + KeyCodec, V> BTreeMap<K, V> {
+    /*token for continuing a scan after `last_seen`, the last key handed out*/
+    pub fn resume_token(&self, last_seen: &K) -> ResumeToken {
+        let mut bytes = vec![TOKEN_VERSION];
+        last_seen
+            .encode_key(&mut bytes)
+            .expect("writing to a Vec never fails");
+        ResumeToken(bytes)
+    }
+
+    /*the entries after the key the token was made from, fails if the token doesn't hold a
+     * key of this map's key type
+     */
+    pub fn resume_from(&self, token: &ResumeToken) -> io::Result<Range<'_, K, V>> {
+        let mut bytes = &token.0[1..];
+        let key = K::decode_key(&mut bytes)?;
+        if !bytes.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "trailing bytes in resume token",
+            ));
+        }
+        Ok(self.range((Bound::Excluded(key), Bound::Unbounded)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_round_trip() {
+        let map: BTreeMap<(u32, String), ()> = BTreeMap::new(2);
+        let token = map.resume_token(&(258, "a\0b".to_string()));
+        let hex = token.to_string();
+        assert!(hex.starts_with("0100000102"));
+        assert_eq!(hex.parse::<ResumeToken>().unwrap(), token);
+        assert_eq!(hex.to_uppercase().parse::<ResumeToken>().unwrap(), token);
+        assert_eq!(
+            ResumeToken::from_bytes(token.as_bytes().to_vec()).unwrap(),
+            token
+        );
+    }
+
+    #[test]
+    fn malformed_tokens_are_rejected() {
+        for hex in ["", "0", "010", "01zz", "+1", "01 2", "0é", "0200000001"] {
+            let error = hex.parse::<ResumeToken>().unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{hex:?}");
+        }
+
+        /*well formed, but not a key of this map*/
+        let map: BTreeMap<u32, ()> = BTreeMap::new(2);
+        let short: ResumeToken = "01000001".parse().unwrap();
+        assert!(map.resume_from(&short).is_err());
+        let long: ResumeToken = "010000000102".parse().unwrap();
+        assert!(map.resume_from(&long).is_err());
+    }
+
+    #[test]
+    fn paginated_scan_yields_every_key_once() {
+        let mut map = BTreeMap::from_sorted_vec(2, (0..50u32).map(|key| (key, ())).collect());
+        let mut seen = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let page: Vec<u32> = match &token {
+                None => map.iter().take(7).map(|(key, _)| *key).collect(),
+                Some(hex) => map
+                    .resume_from(&hex.parse().unwrap())
+                    .unwrap()
+                    .take(7)
+                    .map(|(key, _)| *key)
+                    .collect(),
+            };
+            let Some(&last) = page.last() else {
+                break;
+            };
+            seen.extend(page);
+            token = Some(map.resume_token(&last).to_string());
+            /*the key a token was made from may go away between requests*/
+            map.remove(&last);
+        }
+        assert_eq!(seen, (0..50).collect::<Vec<_>>());
+    }
+}
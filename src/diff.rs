@@ -0,0 +1,242 @@
+use crate::{BTreeMap, Codec};
+use std::io;
+use std::iter::Peekable;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/*one key that differs between two maps, `before` is missing for added keys and `after`
+ * for removed ones, diff_snapshots leaves both out unless asked for the values
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change<K, V> {
+    pub key: K,
+    pub kind: ChangeKind,
+    pub before: Option<V>,
+    pub after: Option<V>,
+}
+
+/*what diff_snapshots found, the counts are those of the kinds in `changes`*/
+#[derive(Debug, Clone, PartialEq)]
+pub struct SnapshotDiff<K, V> {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub changes: Vec<Change<K, V>>,
+}
+
+/*walks two sorted runs of entries side by side, keys present in both with equal values
+ * are skipped
+ */
+struct DiffIter<A: Iterator, B: Iterator> {
+    before: Peekable<A>,
+    after: Peekable<B>,
+}
+
+impl<K: Ord, V: PartialEq, A, B> Iterator for DiffIter<A, B>
+where
+    A: Iterator<Item = (K, V)>,
+    B: Iterator<Item = (K, V)>,
+{
+    type Item = Change<K, V>;
+
+    fn next(&mut self) -> Option<Change<K, V>> {
+        loop {
+            let order = match (self.before.peek(), self.after.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (Some((before, _)), Some((after, _))) => before.cmp(after),
+            };
+
+            match order {
+                std::cmp::Ordering::Less => {
+                    let (key, value) = self.before.next().unwrap();
+                    return Some(Change {
+                        key,
+                        kind: ChangeKind::Removed,
+                        before: Some(value),
+                        after: None,
+                    });
+                }
+                std::cmp::Ordering::Greater => {
+                    let (key, value) = self.after.next().unwrap();
+                    return Some(Change {
+                        key,
+                        kind: ChangeKind::Added,
+                        before: None,
+                        after: Some(value),
+                    });
+                }
+                std::cmp::Ordering::Equal => {
+                    let (key, before) = self.before.next().unwrap();
+                    let (_, after) = self.after.next().unwrap();
+                    if before != after {
+                        return Some(Change {
+                            key,
+                            kind: ChangeKind::Changed,
+                            before: Some(before),
+                            after: Some(after),
+                        });
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<K: Ord, V: PartialEq> BTreeMap<K, V> {
+    /*every key added, removed or changed going from this map to `after`, in key order
+     * one merge of both maps in order, linear in their sizes
+     */
+    pub fn diff<'a>(&'a self, after: &'a Self) -> impl Iterator<Item = Change<&'a K, &'a V>> {
+        DiffIter {
+            before: self.iter().peekable(),
+            after: after.iter().peekable(),
+        }
+    }
+}
+
+impl<K: Ord + Codec, V: Codec + PartialEq> BTreeMap<K, V> {
+    /*audit what happened between two archives written by export_archive, e.g. before
+     * and after a deployment, the values are only kept with `with_values`
+     * there is no command line tool for it, an operator tool wraps this call
+     */
+    pub fn diff_snapshots<P: AsRef<Path>, Q: AsRef<Path>>(
+        before: P,
+        after: Q,
+        with_values: bool,
+    ) -> io::Result<SnapshotDiff<K, V>> {
+        let before = Self::import_archive(before)?;
+        let after = Self::import_archive(after)?;
+
+        let mut diff = SnapshotDiff {
+            added: 0,
+            removed: 0,
+            changed: 0,
+            changes: Vec::new(),
+        };
+        let changes = DiffIter {
+            before: before.into_iter().peekable(),
+            after: after.into_iter().peekable(),
+        };
+        for mut change in changes {
+            match change.kind {
+                ChangeKind::Added => diff.added += 1,
+                ChangeKind::Removed => diff.removed += 1,
+                ChangeKind::Changed => diff.changed += 1,
+            }
+            if !with_values {
+                change.before = None;
+                change.after = None;
+            }
+            diff.changes.push(change);
+        }
+        Ok(diff)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*0..10 against a copy with 0 removed, 5 changed and 10 added*/
+    fn before_and_after() -> (BTreeMap<u32, String>, BTreeMap<u32, String>) {
+        let mut before = BTreeMap::new(2);
+        for key in 0..10 {
+            before.insert(key, key.to_string());
+        }
+        let mut after = BTreeMap::new(3);
+        for key in 1..=10 {
+            after.insert(key, key.to_string());
+        }
+        after.insert(5, "five".to_string());
+        (before, after)
+    }
+
+    fn change(
+        key: u32,
+        kind: ChangeKind,
+        before: Option<&str>,
+        after: Option<&str>,
+    ) -> Change<u32, String> {
+        Change {
+            key,
+            kind,
+            before: before.map(str::to_string),
+            after: after.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn diff_reports_added_removed_and_changed_keys() {
+        let (before, after) = before_and_after();
+        let changes: Vec<_> = before
+            .diff(&after)
+            .map(|change| Change {
+                key: *change.key,
+                kind: change.kind,
+                before: change.before.cloned(),
+                after: change.after.cloned(),
+            })
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                change(0, ChangeKind::Removed, Some("0"), None),
+                change(5, ChangeKind::Changed, Some("5"), Some("five")),
+                change(10, ChangeKind::Added, None, Some("10")),
+            ]
+        );
+        assert_eq!(before.diff(&before).count(), 0);
+        assert_eq!(BTreeMap::new(2).diff(&after).count(), 10);
+    }
+
+    #[test]
+    fn diff_snapshots_reads_exported_archives() {
+        let (before, after) = before_and_after();
+        let dir = std::env::temp_dir();
+        let before_path = dir.join(format!("btreemap-diff-before-{}", std::process::id()));
+        let after_path = dir.join(format!("btreemap-diff-after-{}", std::process::id()));
+        before.export_archive(&before_path).unwrap();
+        after.export_archive(&after_path).unwrap();
+
+        let with_values =
+            BTreeMap::<u32, String>::diff_snapshots(&before_path, &after_path, true).unwrap();
+        let counts_only =
+            BTreeMap::<u32, String>::diff_snapshots(&before_path, &after_path, false).unwrap();
+        std::fs::remove_file(&before_path).unwrap();
+        std::fs::remove_file(&after_path).unwrap();
+
+        assert_eq!(
+            (with_values.added, with_values.removed, with_values.changed),
+            (1, 1, 1)
+        );
+        assert_eq!(
+            with_values.changes,
+            vec![
+                change(0, ChangeKind::Removed, Some("0"), None),
+                change(5, ChangeKind::Changed, Some("5"), Some("five")),
+                change(10, ChangeKind::Added, None, Some("10")),
+            ]
+        );
+        assert_eq!(
+            counts_only,
+            SnapshotDiff {
+                added: 1,
+                removed: 1,
+                changed: 1,
+                changes: vec![
+                    change(0, ChangeKind::Removed, None, None),
+                    change(5, ChangeKind::Changed, None, None),
+                    change(10, ChangeKind::Added, None, None),
+                ],
+            }
+        );
+    }
+}
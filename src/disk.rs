@@ -0,0 +1,1227 @@
+use crate::{Codec, FilePages, KeyCodec, PageStore};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::OpenOptions;
+use std::io::{self, Cursor, Read, Write};
+use std::ops::{Bound, RangeBounds};
+use std::path::Path;
+use std::time::Instant;
+
+/*every node is one page, page 0 is the header, see the README for the layout*/
+pub const PAGE_SIZE: usize = 4096;
+const PAGE_MAGIC: &[u8; 4] = b"BTPG";
+const PAGE_VERSION: u8 = 3;
+
+/*degree for new files, 15 entries of up to ~250 bytes each still fit in a page*/
+const DEFAULT_MIN_DEGREE: usize = 8;
+
+/*pages the cache keeps by default, 4MiB*/
+const DEFAULT_CACHE_PAGES: usize = 1024;
+
+/*page id 0 is the header, so it doubles as "no root"*/
+const NO_PAGE: u64 = 0;
+
+/*leaf flag and key count in front of every page*/
+const PAGE_HEADER_BYTES: usize = 3;
+
+/*every value in a node starts with one of these, an overflowed value is followed by
+ * the first page of its chain and its encoded length instead of the bytes
+ */
+const VALUE_INLINE: u8 = 0;
+const VALUE_OVERFLOW: u8 = 1;
+const OVERFLOW_REF_BYTES: usize = 16;
+
+/*an overflow page is the id of the next page in the chain (0 ends it) followed by data*/
+const OVERFLOW_DATA_BYTES: usize = PAGE_SIZE - 8;
+
+/*returned inside an io::Error of kind InvalidInput when an encoded key or value is
+ * over the map's limits, get it back with `err.get_ref()` and `downcast_ref`
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryTooLarge {
+    pub key_size: usize,
+    pub value_size: usize,
+    pub max_key_size: usize,
+    pub max_value_size: usize,
+}
+
+impl std::fmt::Display for EntryTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "entry too large: key is {} bytes (at most {}), value is {} bytes (at most {})",
+            self.key_size, self.max_key_size, self.value_size, self.max_value_size
+        )
+    }
+}
+
+impl std::error::Error for EntryTooLarge {}
+
+/*where reading or writing the store failed, returned inside an io::Error of the same kind
+ * as the error it wraps, get it back with `err.get_ref()` and `downcast_ref`
+ * the key is debug formatted, the depth counts from the root at 0, either is None when
+ * the operation has no key (warm, flush) or doesn't walk the tree (open, flush)
+ */
+#[derive(Debug)]
+pub struct StorageError {
+    operation: &'static str,
+    key: Option<String>,
+    page_id: u64,
+    depth: Option<usize>,
+    source: io::Error,
+}
+
+impl StorageError {
+    pub fn operation(&self) -> &'static str {
+        self.operation
+    }
+
+    pub fn key(&self) -> Option<&str> {
+        self.key.as_deref()
+    }
+
+    pub fn page_id(&self) -> u64 {
+        self.page_id
+    }
+
+    pub fn depth(&self) -> Option<usize> {
+        self.depth
+    }
+}
+
+impl std::fmt::Display for StorageError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} failed on page {}", self.operation, self.page_id)?;
+        if let Some(depth) = self.depth {
+            write!(f, " at depth {}", depth)?;
+        }
+        if let Some(ref key) = self.key {
+            write!(f, " for key {}", key)?;
+        }
+        write!(f, ": {}", self.source)
+    }
+}
+
+impl std::error::Error for StorageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/*for map_err: wrap an error in a StorageError, an error that already carries one keeps it,
+ * that one was added closer to where things went wrong
+ */
+fn with_context<'a, K: Debug>(
+    operation: &'static str,
+    key: Option<&'a K>,
+    page_id: u64,
+    depth: Option<usize>,
+) -> impl FnOnce(io::Error) -> io::Error + 'a {
+    move |error| {
+        if error
+            .get_ref()
+            .is_some_and(|inner| inner.is::<StorageError>())
+        {
+            return error;
+        }
+        io::Error::new(
+            error.kind(),
+            StorageError {
+                operation,
+                key: key.map(|key| format!("{:?}", key)),
+                page_id,
+                depth,
+                source: error,
+            },
+        )
+    }
+}
+
+/*a node in the page cache, children are page ids
+ * overflow holds the first page of the chain each value was spilled to,
+ * NO_PAGE for values that are inline or not written yet
+ */
+#[derive(Debug)]
+struct Page<K, V> {
+    keys: Vec<K>,
+    values: Vec<V>,
+    overflow: Vec<u64>,
+    children: Vec<u64>,
+    dirty: bool,
+    /*tick of the last access, the smallest ones are evicted first*/
+    last_used: u64,
+}
+
+impl<K, V> Page<K, V> {
+    fn new() -> Self {
+        Page {
+            keys: Vec::new(),
+            values: Vec::new(),
+            overflow: Vec::new(),
+            children: Vec::new(),
+            dirty: true,
+            last_used: 0,
+        }
+    }
+
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+/*page cache and file reads since the map was opened or reset_io_stats(), backup_to isn't
+ * counted, a miss reads its page plus the overflow pages of the node's spilled values,
+ * evictions counts pages dropped from the cache, written back first if they were changed
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IoStats {
+    pub cache_hits: u64,
+    pub cache_misses: u64,
+    pub page_reads: u64,
+    pub bytes_read: u64,
+    pub evictions: u64,
+}
+
+impl IoStats {
+    /*hits out of all page accesses, 1.0 before the first one*/
+    pub fn hit_rate(&self) -> f64 {
+        let accesses = self.cache_hits + self.cache_misses;
+        if accesses == 0 {
+            1.0
+        } else {
+            self.cache_hits as f64 / accesses as f64
+        }
+    }
+
+    /*pages read from the file per page accessed*/
+    pub fn read_amplification(&self) -> f64 {
+        let accesses = self.cache_hits + self.cache_misses;
+        if accesses == 0 {
+            0.0
+        } else {
+            self.page_reads as f64 / accesses as f64
+        }
+    }
+}
+
+/*B-tree kept in a page file instead of in memory, or in any other PageStore
+ * pages are read the first time a lookup or insert walks through them and stay cached
+ * until the cache holds more than its capacity, then the least recently used ones are
+ * dropped (changed ones written back first) before the next operation,
+ * values too large for their share of a node go to a chain of overflow pages, which is
+ * read back together with the node,
+ * changes reach the file on flush(), close(), drop or eviction, there is no log, so a crash
+ * loses the changes since the last flush (and can tear the file if pages were written
+ * since), opened_clean() tells whether the file was closed properly last time
+ * single threaded: every operation that may read pages takes &mut self
+ */
+#[derive(Debug)]
+pub struct DiskBTreeMap<K, V, S = FilePages> {
+    store: S,
+    min_degree: usize,
+    root: u64,
+    page_count: u64,
+    len: u64,
+    header_dirty: bool,
+    max_key_size: usize,
+    max_value_size: usize,
+    cache: HashMap<u64, Page<K, V>>,
+    cache_capacity: usize,
+    /*counts page accesses for the LRU order of the cache*/
+    tick: u64,
+    io_stats: IoStats,
+    /*the header on disk says the file was closed cleanly*/
+    clean: bool,
+    opened_clean: bool,
+    closed: bool,
+    /*Drop can't require KeyCodec and Codec, so open stores the bounded close path here*/
+    close_on_drop: fn(&mut Self) -> io::Result<()>,
+}
+
+impl<K: Ord + KeyCodec + Debug, V: Codec> DiskBTreeMap<K, V> {
+    /*open the tree stored at `path`, an empty one is created if the file doesn't exist*/
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::open_with_min_degree(path, DEFAULT_MIN_DEGREE)
+    }
+
+    /*`min_degree` only applies to a new file, an existing one keeps the degree it was created with
+     * 2 * min_degree - 1 encoded entries plus a few bytes have to fit in one page
+     */
+    pub fn open_with_min_degree<P: AsRef<Path>>(path: P, min_degree: usize) -> io::Result<Self> {
+        Self::open_store(FilePages::open(path)?, min_degree)
+    }
+}
+
+impl<K: Ord + KeyCodec + Debug, V: Codec, S: PageStore> DiskBTreeMap<K, V, S> {
+    /*open the tree kept in `store`, see open_with_min_degree*/
+    pub fn open_store(store: S, min_degree: usize) -> io::Result<Self> {
+        let mut map = DiskBTreeMap {
+            store,
+            min_degree,
+            root: NO_PAGE,
+            page_count: 1,
+            len: 0,
+            header_dirty: true,
+            max_key_size: 0,
+            max_value_size: 0,
+            cache: HashMap::new(),
+            cache_capacity: DEFAULT_CACHE_PAGES,
+            tick: 0,
+            io_stats: IoStats::default(),
+            clean: true,
+            opened_clean: true,
+            /*until it's open, so a failed open doesn't write a header on drop*/
+            closed: true,
+            close_on_drop: Self::finish,
+        };
+        let context = with_context::<K>("open", None, 0, None);
+        if map.store.page_count()? == 0 {
+            /*a file read_header would turn down is never written in the first place*/
+            if !map.degree_fits() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "min_degree must be at least 2 and leave room in a page for its entries",
+                ));
+            }
+            map.write_header().map_err(context)?;
+        } else {
+            map.read_header().map_err(context)?;
+            map.opened_clean = map.clean;
+        }
+
+        map.max_key_size = (map.entry_budget() / 2).min(map.key_size_cap());
+        map.max_value_size = u32::MAX as usize;
+        map.closed = false;
+        Ok(map)
+    }
+
+    /*bytes each entry of a full node may take for the node to still fit in a page
+     * keys get half of it by default (less if that leaves no room for an overflow reference),
+     * a value that doesn't fit in what the largest allowed key leaves over is written to
+     * overflow pages
+     */
+    pub fn entry_budget(&self) -> usize {
+        let max_keys = 2 * self.min_degree - 1;
+        let children = 8 * (max_keys + 1);
+        PAGE_SIZE.saturating_sub(PAGE_HEADER_BYTES + children) / max_keys
+    }
+
+    /*whether nodes of this degree fit in a page with room left for their entries: at least
+     * one key byte, the value tag and an overflow reference
+     */
+    fn degree_fits(&self) -> bool {
+        self.min_degree >= 2 && self.entry_budget() > 1 + OVERFLOW_REF_BYTES
+    }
+
+    /*largest key that still leaves room for the value tag and an overflow reference, so
+     * every entry insert accepts fits in the budget whatever the size of its value
+     */
+    fn key_size_cap(&self) -> usize {
+        self.entry_budget() - 1 - OVERFLOW_REF_BYTES
+    }
+
+    /*limits on encoded sizes checked by insert, they are not stored in the file
+     * raising the key limit pushes more values to overflow pages, it stops short of
+     * entry_budget() by the value tag and an overflow reference, anything above that is
+     * lowered to it so that every accepted entry can be flushed
+     */
+    pub fn set_size_limits(&mut self, max_key_size: usize, max_value_size: usize) {
+        self.max_key_size = max_key_size.min(self.key_size_cap());
+        self.max_value_size = max_value_size;
+    }
+
+    pub fn size_limits(&self) -> (usize, usize) {
+        (self.max_key_size, self.max_value_size)
+    }
+
+    /*largest encoded value kept inside its node, key_size_cap keeps it at least as large
+     * as the overflow reference that replaces a longer one
+     */
+    fn inline_value_size(&self) -> usize {
+        self.entry_budget() - self.max_key_size - 1
+    }
+
+    /*false if the last process to write the file didn't get to close() or drop the map,
+     * the file may then be torn, nothing here can check that, closing this map marks the
+     * file clean again all the same
+     */
+    pub fn opened_clean(&self) -> bool {
+        self.opened_clean
+    }
+
+    /*most pages kept in memory between operations, one operation may briefly go over by
+     * the pages along its path, not stored in the file
+     */
+    pub fn set_cache_capacity(&mut self, pages: usize) {
+        self.cache_capacity = pages;
+    }
+
+    pub fn cache_capacity(&self) -> usize {
+        self.cache_capacity
+    }
+
+    pub fn io_stats(&self) -> IoStats {
+        self.io_stats
+    }
+
+    /*start counting afresh, e.g. around a single operation to see what it costs*/
+    pub fn reset_io_stats(&mut self) {
+        self.io_stats = IoStats::default();
+    }
+
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&mut self, key: &K) -> io::Result<Option<&V>> {
+        self.lookup(key, None)
+    }
+
+    /*like get, but gives up with a TimedOut error instead of reading another page from the
+     * store once `deadline` has passed, a read already started isn't interrupted
+     * the pages read so far stay cached, so a retry picks up where this one stopped
+     */
+    pub fn get_with_deadline(&mut self, key: &K, deadline: Instant) -> io::Result<Option<&V>> {
+        self.lookup(key, Some(deadline))
+    }
+
+    fn lookup(&mut self, key: &K, deadline: Option<Instant>) -> io::Result<Option<&V>> {
+        self.evict()
+            .map_err(with_context("get", Some(key), NO_PAGE, None))?;
+        let mut id = self.root;
+        let mut depth = 0;
+        while id != NO_PAGE {
+            let context = with_context("get", Some(key), id, Some(depth));
+            if !self.cache.contains_key(&id)
+                && deadline.is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                return Err(context(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "deadline passed before the lookup reached its page",
+                )));
+            }
+            self.load(id).map_err(context)?;
+            let page = &self.cache[&id];
+            let pos = page
+                .keys
+                .iter()
+                .position(|k| k >= key)
+                .unwrap_or(page.keys.len());
+
+            if pos < page.keys.len() && page.keys[pos] == *key {
+                return Ok(Some(&self.cache[&id].values[pos]));
+            }
+            id = page.children.get(pos).copied().unwrap_or(NO_PAGE);
+            depth += 1;
+        }
+        Ok(None)
+    }
+
+    /*same top down splitting as the in memory tree, on page ids instead of boxes*/
+    pub fn insert(&mut self, key: K, value: V) -> io::Result<Option<V>> {
+        self.check_size(&key, &value)?;
+        self.evict()
+            .map_err(with_context("insert", Some(&key), NO_PAGE, None))?;
+        let max_keys = 2 * self.min_degree - 1;
+        if self.root == NO_PAGE {
+            self.root = self.allocate(Page::new());
+            self.header_dirty = true;
+        }
+
+        self.load(self.root)
+            .map_err(with_context("insert", Some(&key), self.root, Some(0)))?;
+        if self.cache[&self.root].keys.len() == max_keys {
+            let mut new_root = Page::new();
+            new_root.children.push(self.root);
+            self.root = self.allocate(new_root);
+            self.header_dirty = true;
+            self.split_child(self.root, 0)?;
+        }
+
+        let mut id = self.root;
+        let mut depth = 0;
+        loop {
+            let page = self.cache.get_mut(&id).unwrap();
+            let mut pos = page
+                .keys
+                .iter()
+                .position(|k| *k >= key)
+                .unwrap_or(page.keys.len());
+
+            if pos < page.keys.len() && page.keys[pos] == key {
+                page.dirty = true;
+                page.overflow[pos] = NO_PAGE;
+                return Ok(Some(std::mem::replace(&mut page.values[pos], value)));
+            }
+            if page.is_leaf() {
+                page.keys.insert(pos, key);
+                page.values.insert(pos, value);
+                page.overflow.insert(pos, NO_PAGE);
+                page.dirty = true;
+                self.len += 1;
+                self.header_dirty = true;
+                return Ok(None);
+            }
+
+            let child = page.children[pos];
+            depth += 1;
+            self.load(child)
+                .map_err(with_context("insert", Some(&key), child, Some(depth)))?;
+            if self.cache[&child].keys.len() == max_keys {
+                self.split_child(id, pos)?;
+                let page = self.cache.get_mut(&id).unwrap();
+                match key.cmp(&page.keys[pos]) {
+                    std::cmp::Ordering::Less => {}
+                    std::cmp::Ordering::Equal => {
+                        page.dirty = true;
+                        page.overflow[pos] = NO_PAGE;
+                        return Ok(Some(std::mem::replace(&mut page.values[pos], value)));
+                    }
+                    std::cmp::Ordering::Greater => pos += 1,
+                }
+            }
+            id = self.cache[&id].children[pos];
+        }
+    }
+
+    /*read every page that can hold keys in `range` into the cache before traffic arrives,
+     * `..` warms the whole tree, returns how many pages had to come from the file
+     * a range spanning more pages than the cache capacity only leaves its last ones cached
+     * the map is single threaded, to warm in the background run this from a Maintenance
+     * task on the shared map instead
+     */
+    pub fn warm<R: RangeBounds<K>>(&mut self, range: R) -> io::Result<usize> {
+        let mut loaded = 0;
+        let mut pending = vec![(self.root, 0)];
+        while let Some((id, depth)) = pending.pop() {
+            if id == NO_PAGE {
+                continue;
+            }
+            loaded += usize::from(!self.cache.contains_key(&id));
+            self.evict()
+                .map_err(with_context::<K>("warm", None, id, Some(depth)))?;
+            self.load(id)
+                .map_err(with_context::<K>("warm", None, id, Some(depth)))?;
+
+            /*child i only holds keys between keys[i - 1] and keys[i]*/
+            let page = &self.cache[&id];
+            for (i, &child) in page.children.iter().enumerate() {
+                let past_end = i > 0
+                    && match range.end_bound() {
+                        Bound::Included(end) | Bound::Excluded(end) => page.keys[i - 1] >= *end,
+                        Bound::Unbounded => false,
+                    };
+                let before_start = i < page.keys.len()
+                    && match range.start_bound() {
+                        Bound::Included(start) | Bound::Excluded(start) => page.keys[i] <= *start,
+                        Bound::Unbounded => false,
+                    };
+                if !past_end && !before_start {
+                    pending.push((child, depth + 1));
+                }
+            }
+        }
+        Ok(loaded)
+    }
+
+    /*write every changed page and the header, then sync the file*/
+    pub fn flush(&mut self) -> io::Result<()> {
+        let mut dirty: Vec<u64> = self
+            .cache
+            .iter()
+            .filter(|(_, page)| page.dirty)
+            .map(|(&id, _)| id)
+            .collect();
+        dirty.sort_unstable();
+
+        if self.header_dirty || !dirty.is_empty() {
+            self.mark_unclean()?;
+        }
+        for id in dirty {
+            /*out of the cache while it's encoded, spilling a value allocates pages*/
+            let mut page = self.cache.remove(&id).unwrap();
+            let written = self.write_back(id, &mut page);
+            page.dirty = written.is_err();
+            self.cache.insert(id, page);
+            written.map_err(with_context::<K>("flush", None, id, None))?;
+        }
+        if self.header_dirty {
+            self.write_header()?;
+        }
+        self.store.sync()
+    }
+
+    /*flush, then copy every page to a new file at `path` that opens as this map does now
+     * the copy is marked clean unless this file wasn't when it was opened
+     * this is not an online backup: pages are overwritten in place and not copied on write,
+     * so the map stays borrowed until the whole file is copied and synced, and every reader
+     * and writer sharing it (e.g. behind a Mutex) waits that long, which grows with the
+     * file, not with the changes since the last backup
+     */
+    pub fn backup_to<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.flush()?;
+        let mut backup = OpenOptions::new().write(true).create_new(true).open(path)?;
+        backup.write_all(&self.header_bytes(self.opened_clean)?)?;
+
+        let mut bytes = vec![0; PAGE_SIZE];
+        for id in 1..self.page_count {
+            self.store.read_page(id, &mut bytes)?;
+            backup.write_all(&bytes)?;
+        }
+        backup.sync_all()
+    }
+
+    /*flush and mark the file clean, dropping the map does the same but can't report errors*/
+    pub fn close(mut self) -> io::Result<()> {
+        self.finish()
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.flush()?;
+        if !self.clean {
+            self.clean = true;
+            self.write_header()?;
+            self.store.sync()?;
+        }
+        self.closed = true;
+        Ok(())
+    }
+
+    fn check_size(&self, key: &K, value: &V) -> io::Result<()> {
+        let key_size = key.to_key_bytes().len();
+        let mut bytes = Vec::new();
+        value.encode(&mut bytes)?;
+        let value_size = bytes.len();
+
+        if key_size > self.max_key_size || value_size > self.max_value_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                EntryTooLarge {
+                    key_size,
+                    value_size,
+                    max_key_size: self.max_key_size,
+                    max_value_size: self.max_value_size,
+                },
+            ));
+        }
+        Ok(())
+    }
+
+    /*the file stops being clean before the first page is overwritten*/
+    fn mark_unclean(&mut self) -> io::Result<()> {
+        if self.clean {
+            self.clean = false;
+            self.write_header()?;
+            self.store.sync()?;
+        }
+        Ok(())
+    }
+
+    fn write_back(&mut self, id: u64, page: &mut Page<K, V>) -> io::Result<()> {
+        let bytes = self.encode_page(page)?;
+        self.write_page(id, &bytes)
+    }
+
+    /*drop the least recently used pages once the cache is over capacity, down to three
+     * quarters of it so the next few operations don't sort the cache again
+     * only called between operations, nothing holds on to a cached page then
+     */
+    fn evict(&mut self) -> io::Result<()> {
+        if self.cache.len() <= self.cache_capacity {
+            return Ok(());
+        }
+        let mut by_age: Vec<(u64, u64)> = self
+            .cache
+            .iter()
+            .map(|(&id, page)| (page.last_used, id))
+            .collect();
+        by_age.sort_unstable();
+
+        let keep = self.cache_capacity - self.cache_capacity / 4;
+        for &(_, id) in &by_age[..self.cache.len() - keep] {
+            let mut page = self.cache.remove(&id).unwrap();
+            if page.dirty {
+                let written = self
+                    .mark_unclean()
+                    .and_then(|_| self.write_back(id, &mut page));
+                if let Err(error) = written {
+                    self.cache.insert(id, page);
+                    return Err(error);
+                }
+            }
+            self.io_stats.evictions += 1;
+        }
+        Ok(())
+    }
+
+    /*split the full child at `index` of page `parent` around its middle key*/
+    fn split_child(&mut self, parent: u64, index: usize) -> io::Result<()> {
+        let child = self.cache[&parent].children[index];
+        self.load(child)?;
+
+        let page = self.cache.get_mut(&child).unwrap();
+        let mut right = Page::new();
+        right.keys = page.keys.split_off(self.min_degree);
+        right.values = page.values.split_off(self.min_degree);
+        right.overflow = page.overflow.split_off(self.min_degree);
+        if !page.is_leaf() {
+            right.children = page.children.split_off(self.min_degree);
+        }
+        let key = page.keys.pop().unwrap();
+        let value = page.values.pop().unwrap();
+        let overflow = page.overflow.pop().unwrap();
+        page.dirty = true;
+
+        let right = self.allocate(right);
+        let parent = self.cache.get_mut(&parent).unwrap();
+        parent.keys.insert(index, key);
+        parent.values.insert(index, value);
+        parent.overflow.insert(index, overflow);
+        parent.children.insert(index + 1, right);
+        parent.dirty = true;
+        Ok(())
+    }
+
+    /*pages are handed out from the end of the file, nothing is freed yet*/
+    fn allocate(&mut self, mut page: Page<K, V>) -> u64 {
+        let id = self.page_count;
+        self.page_count += 1;
+        self.header_dirty = true;
+        self.tick += 1;
+        page.last_used = self.tick;
+        self.cache.insert(id, page);
+        id
+    }
+
+    fn load(&mut self, id: u64) -> io::Result<()> {
+        self.tick += 1;
+        if let Some(page) = self.cache.get_mut(&id) {
+            page.last_used = self.tick;
+            self.io_stats.cache_hits += 1;
+            return Ok(());
+        }
+
+        self.io_stats.cache_misses += 1;
+        let mut bytes = vec![0; PAGE_SIZE];
+        self.store.read_page(id, &mut bytes)?;
+        self.io_stats.page_reads += 1;
+        self.io_stats.bytes_read += PAGE_SIZE as u64;
+        let mut page = self.decode_page(&bytes)?;
+        page.last_used = self.tick;
+        self.cache.insert(id, page);
+        Ok(())
+    }
+
+    fn encode_page(&mut self, page: &mut Page<K, V>) -> io::Result<Vec<u8>> {
+        let count = u16::try_from(page.keys.len())
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "too many keys for a page"))?;
+
+        let mut bytes = Vec::with_capacity(PAGE_SIZE);
+        u8::from(page.is_leaf()).encode(&mut bytes)?;
+        count.encode(&mut bytes)?;
+        for key in &page.keys {
+            key.encode_key(&mut bytes)?;
+        }
+        let inline_value_size = self.inline_value_size();
+        for (value, overflow) in page.values.iter().zip(&mut page.overflow) {
+            let mut encoded = Vec::new();
+            value.encode(&mut encoded)?;
+            if encoded.len() <= inline_value_size {
+                VALUE_INLINE.encode(&mut bytes)?;
+                bytes.extend_from_slice(&encoded);
+                continue;
+            }
+
+            if *overflow == NO_PAGE {
+                *overflow = self.write_overflow(&encoded)?;
+            }
+            VALUE_OVERFLOW.encode(&mut bytes)?;
+            overflow.encode(&mut bytes)?;
+            (encoded.len() as u64).encode(&mut bytes)?;
+        }
+        for child in &page.children {
+            child.encode(&mut bytes)?;
+        }
+
+        if bytes.len() > PAGE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "node does not fit in a page, use a lower min_degree",
+            ));
+        }
+        bytes.resize(PAGE_SIZE, 0);
+        Ok(bytes)
+    }
+
+    fn decode_page(&mut self, bytes: &[u8]) -> io::Result<Page<K, V>> {
+        let mut reader = Cursor::new(bytes);
+        let is_leaf = bool::decode(&mut reader)?;
+        let count = u16::decode(&mut reader)? as usize;
+
+        let mut page = Page::new();
+        page.dirty = false;
+        for _ in 0..count {
+            page.keys.push(K::decode_key(&mut reader)?);
+        }
+        for _ in 0..count {
+            match u8::decode(&mut reader)? {
+                VALUE_INLINE => {
+                    page.values.push(V::decode(&mut reader)?);
+                    page.overflow.push(NO_PAGE);
+                }
+                VALUE_OVERFLOW => {
+                    let first = u64::decode(&mut reader)?;
+                    let len = u64::decode(&mut reader)? as usize;
+                    let encoded = self.read_overflow(first, len)?;
+                    page.values.push(V::decode(&mut &encoded[..])?);
+                    page.overflow.push(first);
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "invalid value tag in page",
+                    ))
+                }
+            }
+        }
+        if !is_leaf {
+            for _ in 0..=count {
+                page.children.push(u64::decode(&mut reader)?);
+            }
+        }
+        Ok(page)
+    }
+
+    /*write an encoded value to fresh consecutive pages, returns the first one
+     * the chain of a value that is replaced later is not reused
+     */
+    fn write_overflow(&mut self, encoded: &[u8]) -> io::Result<u64> {
+        let first = self.page_count;
+        let chunks = encoded.chunks(OVERFLOW_DATA_BYTES);
+        let count = chunks.len() as u64;
+        self.page_count += count;
+        self.header_dirty = true;
+
+        for (i, chunk) in chunks.enumerate() {
+            let id = first + i as u64;
+            let next = if id + 1 < first + count {
+                id + 1
+            } else {
+                NO_PAGE
+            };
+            let mut bytes = Vec::with_capacity(PAGE_SIZE);
+            next.encode(&mut bytes)?;
+            bytes.extend_from_slice(chunk);
+            bytes.resize(PAGE_SIZE, 0);
+            self.write_page(id, &bytes)?;
+        }
+        Ok(first)
+    }
+
+    fn read_overflow(&mut self, first: u64, len: usize) -> io::Result<Vec<u8>> {
+        /*a corrupt length is only allocated for if the store has the pages to hold it*/
+        let pages = self.store.page_count()?.saturating_sub(1);
+        let stored = usize::try_from(pages)
+            .unwrap_or(usize::MAX)
+            .saturating_mul(OVERFLOW_DATA_BYTES);
+        if len > stored {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "overflow value longer than the page file",
+            ));
+        }
+        let mut encoded = Vec::with_capacity(len);
+        let mut bytes = vec![0; PAGE_SIZE];
+        let mut id = first;
+        while encoded.len() < len {
+            if id == NO_PAGE {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "overflow chain ends before the value does",
+                ));
+            }
+            self.store.read_page(id, &mut bytes)?;
+            self.io_stats.page_reads += 1;
+            self.io_stats.bytes_read += PAGE_SIZE as u64;
+            id = u64::decode(&mut &bytes[..8])?;
+            let take = (len - encoded.len()).min(OVERFLOW_DATA_BYTES);
+            encoded.extend_from_slice(&bytes[8..8 + take]);
+        }
+        Ok(encoded)
+    }
+
+    fn write_page(&mut self, id: u64, bytes: &[u8]) -> io::Result<()> {
+        self.store.write_page(id, bytes)
+    }
+
+    fn write_header(&mut self) -> io::Result<()> {
+        let bytes = self.header_bytes(self.clean)?;
+        self.write_page(0, &bytes)?;
+        self.header_dirty = false;
+        Ok(())
+    }
+
+    fn header_bytes(&self, clean: bool) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(PAGE_SIZE);
+        bytes.extend_from_slice(PAGE_MAGIC);
+        PAGE_VERSION.encode(&mut bytes)?;
+        (PAGE_SIZE as u32).encode(&mut bytes)?;
+        (self.min_degree as u32).encode(&mut bytes)?;
+        self.root.encode(&mut bytes)?;
+        self.page_count.encode(&mut bytes)?;
+        self.len.encode(&mut bytes)?;
+        clean.encode(&mut bytes)?;
+        bytes.resize(PAGE_SIZE, 0);
+        Ok(bytes)
+    }
+
+    fn read_header(&mut self) -> io::Result<()> {
+        let mut bytes = vec![0; PAGE_SIZE];
+        self.store.read_page(0, &mut bytes)?;
+
+        let mut reader = Cursor::new(&bytes[..]);
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != PAGE_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "not a page file",
+            ));
+        }
+        let version = u8::decode(&mut reader)?;
+        if version != PAGE_VERSION && version != 2 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "unsupported page file version",
+            ));
+        }
+        if u32::decode(&mut reader)? as usize != PAGE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "page file uses a different page size",
+            ));
+        }
+
+        /*the degree sizes every node read from now on, a corrupt one must not get that far*/
+        self.min_degree = u32::decode(&mut reader)? as usize;
+        if !self.degree_fits() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "page file has an invalid min_degree",
+            ));
+        }
+        self.root = u64::decode(&mut reader)?;
+        self.page_count = u64::decode(&mut reader)?;
+        self.len = u64::decode(&mut reader)?;
+        /*version 2 has padding where the clean flag is and knows nothing about crashes, the
+         * next header written upgrades the file to version 3
+         */
+        self.clean = bool::decode(&mut reader)? || version == 2;
+        self.header_dirty = false;
+        Ok(())
+    }
+}
+
+impl<K, V, S> Drop for DiskBTreeMap<K, V, S> {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = (self.close_on_drop)(self);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MemoryPages;
+    use std::collections::BTreeMap as StdMap;
+
+    /*xorshift, enough to pick operations and keys reproducibly*/
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u32) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as u32
+        }
+    }
+
+    type MemoryMap = DiskBTreeMap<u32, String, MemoryPages>;
+
+    fn assert_same(map: &mut MemoryMap, model: &StdMap<u32, String>, step: usize) {
+        assert_eq!(map.len(), model.len(), "step {step}");
+        for (key, value) in model {
+            assert_eq!(map.get(key).unwrap(), Some(value), "step {step}: key {key}");
+        }
+    }
+
+    /*random inserts and lookups against a std map, with flushes and reopens in between
+     * so that pages (and overflow chains for the long values) are read back from the store
+     */
+    #[test]
+    fn random_operations_match_std_across_reopens() {
+        for (seed, min_degree) in [(1, 2), (2, 3), (3, 8)] {
+            let pages = MemoryPages::new();
+            let mut map = MemoryMap::open_store(pages.clone(), min_degree).unwrap();
+            let mut model = StdMap::new();
+            let mut rng = Rng(seed);
+
+            for step in 0..1500 {
+                let key = rng.below(500);
+                match rng.below(20) {
+                    0..=11 => {
+                        let len = match rng.below(10) {
+                            0 => 5000 + rng.below(5000),
+                            _ => rng.below(20),
+                        };
+                        let value = "v".repeat(len as usize);
+                        assert_eq!(
+                            map.insert(key, value.clone()).unwrap(),
+                            model.insert(key, value)
+                        );
+                    }
+                    12..=16 => assert_eq!(map.get(&key).unwrap(), model.get(&key)),
+                    17 => map.flush().unwrap(),
+                    _ => {
+                        map.close().unwrap();
+                        map = MemoryMap::open_store(pages.clone(), min_degree).unwrap();
+                        assert!(map.opened_clean());
+                        assert_same(&mut map, &model, step);
+                    }
+                }
+                assert_eq!(map.len(), model.len(), "step {step}");
+            }
+            assert_same(&mut map, &model, 1500);
+        }
+    }
+
+    /*a map that is neither closed nor dropped, like after a crash in the middle of a write*/
+    fn crash(map: MemoryMap) {
+        std::mem::forget(map);
+    }
+
+    #[test]
+    fn reopen_keeps_the_entries_and_the_clean_flag() {
+        let pages = MemoryPages::new();
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        for key in 0..100 {
+            map.insert(key, key.to_string()).unwrap();
+        }
+        drop(map);
+
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        assert!(map.opened_clean());
+        assert_eq!(map.len(), 100);
+        assert_eq!(map.get(&42).unwrap().map(String::as_str), Some("42"));
+        map.close().unwrap();
+    }
+
+    #[test]
+    fn crash_after_a_flush_opens_unclean_until_closed() {
+        let pages = MemoryPages::new();
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        map.insert(1, "one".to_string()).unwrap();
+        map.flush().unwrap();
+        crash(map);
+
+        let map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        assert!(!map.opened_clean());
+        map.close().unwrap();
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        assert!(map.opened_clean());
+        assert_eq!(map.get(&1).unwrap().map(String::as_str), Some("one"));
+
+        /*an unclean file that gets written to and closed is clean again as well*/
+        map.insert(2, "two".to_string()).unwrap();
+        map.flush().unwrap();
+        crash(map);
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        assert!(!map.opened_clean());
+        map.insert(3, "three".to_string()).unwrap();
+        map.close().unwrap();
+        assert!(MemoryMap::open_store(pages, 2).unwrap().opened_clean());
+    }
+
+    #[test]
+    fn crash_before_any_flush_leaves_the_file_clean() {
+        let pages = MemoryPages::new();
+        MemoryMap::open_store(pages.clone(), 2)
+            .unwrap()
+            .close()
+            .unwrap();
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        map.insert(1, "one".to_string()).unwrap();
+        crash(map);
+
+        let map = MemoryMap::open_store(pages, 2).unwrap();
+        assert!(map.opened_clean());
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn version_2_files_open_as_clean() {
+        let pages = MemoryPages::new();
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        map.insert(1, "one".to_string()).unwrap();
+        map.close().unwrap();
+
+        /*the version byte follows the magic, the clean flag was padding in version 2*/
+        let mut store = pages.clone();
+        let mut header = vec![0; PAGE_SIZE];
+        store.read_page(0, &mut header).unwrap();
+        header[4] = 2;
+        header[4 + 1 + 4 + 4 + 8 + 8 + 8] = 0;
+        store.write_page(0, &header).unwrap();
+
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        assert!(map.opened_clean());
+        assert_eq!(map.get(&1).unwrap().map(String::as_str), Some("one"));
+        map.insert(2, "two".to_string()).unwrap();
+        map.close().unwrap();
+
+        store.read_page(0, &mut header).unwrap();
+        assert_eq!(header[4], PAGE_VERSION);
+    }
+
+    /*header offsets: magic, version, page size, min_degree, root*/
+    const MIN_DEGREE_AT: usize = 4 + 1 + 4;
+    const ROOT_AT: usize = MIN_DEGREE_AT + 4;
+
+    fn patch_page(pages: &MemoryPages, id: u64, at: usize, patch: &[u8]) {
+        let mut store = pages.clone();
+        let mut page = vec![0; PAGE_SIZE];
+        store.read_page(id, &mut page).unwrap();
+        page[at..at + patch.len()].copy_from_slice(patch);
+        store.write_page(id, &page).unwrap();
+    }
+
+    #[test]
+    fn invalid_min_degree_in_the_header_is_rejected() {
+        let pages = MemoryPages::new();
+        MemoryMap::open_store(pages.clone(), 2)
+            .unwrap()
+            .close()
+            .unwrap();
+
+        for min_degree in [0u32, 1, 1000, u32::MAX] {
+            patch_page(&pages, 0, MIN_DEGREE_AT, &min_degree.to_le_bytes());
+            let error = MemoryMap::open_store(pages.clone(), 2).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{min_degree}");
+            /*the failed open must not write its own header over the file*/
+            let mut header = vec![0; PAGE_SIZE];
+            pages.clone().read_page(0, &mut header).unwrap();
+            assert_eq!(header[MIN_DEGREE_AT..ROOT_AT], min_degree.to_le_bytes());
+        }
+    }
+
+    #[test]
+    fn corrupt_overflow_length_is_rejected_before_allocating() {
+        let pages = MemoryPages::new();
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        map.insert(1, "v".repeat(10_000)).unwrap();
+        map.close().unwrap();
+
+        /*the root is the only node: leaf flag, key count, the key, then the value tag,
+         * the first overflow page and the length
+         */
+        let mut header = vec![0; PAGE_SIZE];
+        pages.clone().read_page(0, &mut header).unwrap();
+        let root = u64::from_le_bytes(header[ROOT_AT..ROOT_AT + 8].try_into().unwrap());
+        let len_at = 1 + 2 + 1u32.to_key_bytes().len() + 1 + 8;
+        patch_page(&pages, root, len_at, &u64::MAX.to_le_bytes());
+
+        let mut map = MemoryMap::open_store(pages, 2).unwrap();
+        let error = map.get(&1).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn invalid_min_degree_for_a_new_file_is_rejected_before_writing() {
+        for min_degree in [0, 1, 1000] {
+            let mut pages = MemoryPages::new();
+            let error = MemoryMap::open_store(pages.clone(), min_degree).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidInput, "{min_degree}");
+            assert_eq!(pages.page_count().unwrap(), 0);
+        }
+    }
+
+    #[test]
+    fn entries_at_the_size_limits_can_be_flushed() {
+        /*min_degree 100 leaves 12 bytes per entry, less than a key and an overflow reference*/
+        let error = MemoryMap::open_store(MemoryPages::new(), 100).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        for min_degree in [2, 8, 32, 60] {
+            let pages = MemoryPages::new();
+            let mut map: DiskBTreeMap<Vec<u8>, String, _> =
+                DiskBTreeMap::open_store(pages.clone(), min_degree).unwrap();
+            map.set_size_limits(usize::MAX, u32::MAX as usize);
+            let (max_key_size, _) = map.size_limits();
+            let inline = map.inline_value_size();
+
+            /*keys of exactly max_key_size encoded bytes (content plus a 2 byte terminator),
+             * values right at the inline limit and one past it
+             */
+            let entries = 4 * (2 * min_degree - 1);
+            for n in 0..entries {
+                let mut key = vec![1u8; max_key_size - 2];
+                key[0] = 1 + (n % 255) as u8;
+                key[1] = 1 + (n / 255) as u8;
+                let value = "v".repeat(inline - 4 + n % 2);
+                map.insert(key, value).unwrap();
+            }
+            map.flush().unwrap();
+            map.close().unwrap();
+
+            let map: DiskBTreeMap<Vec<u8>, String, _> =
+                DiskBTreeMap::open_store(pages, min_degree).unwrap();
+            assert_eq!(map.len(), entries, "{min_degree}");
+        }
+    }
+
+    #[test]
+    fn bounded_cache_writes_back_evicted_pages() {
+        let pages = MemoryPages::new();
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        map.set_cache_capacity(8);
+        let mut model = StdMap::new();
+        let mut rng = Rng(9);
+
+        for _ in 0..2000 {
+            let key = rng.below(1000);
+            let value = key.to_string();
+            assert_eq!(
+                map.insert(key, value.clone()).unwrap(),
+                model.insert(key, value)
+            );
+            let probe = rng.below(1000);
+            assert_eq!(map.get(&probe).unwrap(), model.get(&probe));
+            /*over capacity by at most the path of the last operation*/
+            assert!(map.cache.len() <= 8 + 2 * 12, "{}", map.cache.len());
+        }
+        assert!(map.io_stats().evictions > 0);
+        assert_same(&mut map, &model, 2000);
+
+        map.close().unwrap();
+        let mut map = MemoryMap::open_store(pages.clone(), 2).unwrap();
+        assert!(map.opened_clean());
+        map.set_cache_capacity(8);
+        assert_same(&mut map, &model, 2000);
+
+        /*evicted pages reach the store before any flush, so the file is unclean from then on*/
+        for key in 1000..1100 {
+            map.insert(key, key.to_string()).unwrap();
+        }
+        crash(map);
+        assert!(!MemoryMap::open_store(pages, 2).unwrap().opened_clean());
+    }
+}
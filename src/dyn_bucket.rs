@@ -0,0 +1,83 @@
+use crate::BTreeMap;
+use std::any::Any;
+use std::borrow::Borrow;
+
+/*a value of any type plus the name of that type, for type_name and Debug output*/
+#[derive(Debug)]
+struct DynValue {
+    value: Box<dyn Any + Send>,
+    type_name: &'static str,
+}
+
+/*sorted registry whose values can each be of a different type
+ * values are boxed as Any and every typed accessor checks the type at runtime, asking for
+ * the wrong type reads as a missing entry, type_name says what is really stored
+ */
+#[derive(Debug)]
+pub struct DynBucket<K> {
+    map: BTreeMap<K, DynValue>,
+}
+
+impl<K: Ord> DynBucket<K> {
+    pub fn new(min_degree: usize) -> Self {
+        DynBucket {
+            map: BTreeMap::new(min_degree),
+        }
+    }
+
+    /*returns the value that was replaced, whatever its type was*/
+    pub fn insert<T: Any + Send>(&mut self, key: K, value: T) -> Option<Box<dyn Any + Send>> {
+        let value = DynValue {
+            value: Box::new(value),
+            type_name: std::any::type_name::<T>(),
+        };
+        self.map.insert(key, value).map(|old| old.value)
+    }
+
+    /*None if the key is missing or holds something other than a T
+     * the key type isn't generic here, so `get::<T>(&key)` needs no more annotations
+     */
+    pub fn get<T: Any>(&self, key: &K) -> Option<&T> {
+        self.map.get(key)?.value.downcast_ref()
+    }
+
+    pub fn get_mut<T: Any>(&mut self, key: &K) -> Option<&mut T> {
+        self.map.get_mut(key)?.value.downcast_mut()
+    }
+
+    /*the name std::any::type_name gave the stored type, only meant for messages*/
+    pub fn type_name<Q>(&self, key: &Q) -> Option<&'static str>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.get(key).map(|stored| stored.type_name)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<Box<dyn Any + Send>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.remove(key).map(|stored| stored.value)
+    }
+
+    /*the entries holding a T in key order, the others are skipped*/
+    pub fn iter_of<T: Any>(&self) -> impl Iterator<Item = (&K, &T)> {
+        self.map
+            .iter()
+            .filter_map(|(key, stored)| Some((key, stored.value.downcast_ref()?)))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.map.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
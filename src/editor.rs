@@ -0,0 +1,157 @@
+use crate::view::in_range;
+use crate::BTreeMap;
+use std::borrow::Borrow;
+use std::ops::Bound;
+
+/*scoped transaction over the keys of a map inside a range
+ * inserts and removes are staged on the side and reads through the editor see them,
+ * commit applies them all at once, dropping the editor throws them away
+ * the map stays mutably borrowed meanwhile, so nobody sees a half applied edit
+ */
+#[derive(Debug)]
+pub struct RangeEditor<'a, K, V> {
+    map: &'a mut BTreeMap<K, V>,
+    start: Bound<K>,
+    end: Bound<K>,
+    /*None stages a remove*/
+    staged: BTreeMap<K, Option<V>>,
+}
+
+impl<'a, K: Ord + Clone, V> RangeEditor<'a, K, V> {
+    pub(crate) fn new(map: &'a mut BTreeMap<K, V>, start: Bound<K>, end: Bound<K>) -> Self {
+        let min_degree = map.min_degree();
+        RangeEditor {
+            map,
+            start,
+            end,
+            staged: BTreeMap::new(min_degree),
+        }
+    }
+
+    /*the value as it would be after commit, None for keys outside the range*/
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if !in_range(&self.start, &self.end, key) {
+            return None;
+        }
+        match self.staged.get(key) {
+            Some(staged) => staged.as_ref(),
+            None => self.map.get(key),
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /*stage an insert, a key outside the range is handed back untouched*/
+    pub fn insert(&mut self, key: K, value: V) -> Result<(), (K, V)> {
+        if !in_range(&self.start, &self.end, &key) {
+            return Err((key, value));
+        }
+        self.staged.insert(key, Some(value));
+        Ok(())
+    }
+
+    /*stage a remove, false if the key isn't in the range or already gone*/
+    pub fn remove(&mut self, key: &K) -> bool {
+        if !self.contains_key(key) {
+            return false;
+        }
+        self.staged.insert(key.clone(), None);
+        true
+    }
+
+    /*number of keys with a staged change*/
+    pub fn staged_len(&self) -> usize {
+        self.staged.len()
+    }
+
+    /*apply every staged change to the map in key order*/
+    pub fn commit(self) {
+        for (key, change) in self.staged {
+            match change {
+                Some(value) => {
+                    self.map.insert(key, value);
+                }
+                None => {
+                    self.map.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTreeMap;
+
+    fn sample() -> BTreeMap<u32, u32> {
+        let mut map = BTreeMap::new(2);
+        for key in 0..20 {
+            map.insert(key, key);
+        }
+        map
+    }
+
+    #[test]
+    fn commit_applies_staged_changes() {
+        let mut map = sample();
+        let mut editor = map.edit_range(5..10);
+        editor.insert(5, 50).unwrap();
+        assert!(editor.remove(&6));
+        assert!(!editor.remove(&6));
+        assert_eq!(editor.get(&5), Some(&50));
+        assert_eq!(editor.get(&6), None);
+        assert_eq!(editor.staged_len(), 2);
+        editor.commit();
+
+        assert_eq!(map.get(&5), Some(&50));
+        assert_eq!(map.get(&6), None);
+        assert_eq!(map.len(), 19);
+        let expected = (0..20)
+            .filter(|&key| key != 6)
+            .map(|key| (key, if key == 5 { 50 } else { key }));
+        assert!(map.iter().map(|(k, v)| (*k, *v)).eq(expected));
+    }
+
+    #[test]
+    fn dropping_without_commit_leaves_the_map_alone() {
+        let mut map = sample();
+        {
+            let mut editor = map.edit_range(..);
+            editor.insert(100, 100).unwrap();
+            editor.insert(3, 30).unwrap();
+            assert!(editor.remove(&4));
+        }
+        assert!(map
+            .iter()
+            .map(|(k, v)| (*k, *v))
+            .eq((0..20).map(|key| (key, key))));
+    }
+
+    #[test]
+    fn writes_outside_the_range_are_rejected() {
+        let mut map = sample();
+        let mut editor = map.edit_range(5..=10);
+        assert_eq!(editor.insert(4, 0), Err((4, 0)));
+        assert_eq!(editor.insert(11, 0), Err((11, 0)));
+        assert!(!editor.remove(&4));
+        assert!(!editor.remove(&11));
+        assert_eq!(editor.get(&4), None);
+        assert_eq!(editor.staged_len(), 0);
+        editor.insert(10, 0).unwrap();
+        editor.commit();
+
+        assert_eq!(map.get(&4), Some(&4));
+        assert_eq!(map.get(&11), Some(&11));
+        assert_eq!(map.get(&10), Some(&0));
+    }
+}
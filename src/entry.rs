@@ -0,0 +1,231 @@
+use crate::node::BTreeNode;
+use crate::BTreeMap;
+
+/*a slot in the map found by one descent, occupied or not
+ * both kinds remember the child indices from the root down to their node, so
+ * reading, updating or filling the slot never compares keys a second time
+ */
+#[derive(Debug)]
+pub enum Entry<'a, K, V> {
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+#[derive(Debug)]
+pub struct VacantEntry<'a, K, V> {
+    map: &'a mut BTreeMap<K, V>,
+    key: K,
+    path: Vec<usize>,
+    index: usize,
+}
+
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, K, V> {
+    map: &'a mut BTreeMap<K, V>,
+    path: Vec<usize>,
+    index: usize,
+}
+
+impl<K: Ord, V> BTreeMap<K, V> {
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        let mut path = Vec::new();
+        let mut node = self.root.as_ref();
+        while let Some(current) = node {
+            let pos = current
+                .keys
+                .iter()
+                .position(|k| *k >= key)
+                .unwrap_or(current.keys.len());
+
+            if pos < current.keys.len() && current.keys[pos] == key {
+                return Entry::Occupied(OccupiedEntry {
+                    map: self,
+                    path,
+                    index: pos,
+                });
+            }
+            if current.is_leaf {
+                return Entry::Vacant(VacantEntry {
+                    map: self,
+                    key,
+                    path,
+                    index: pos,
+                });
+            }
+            path.push(pos);
+            node = current.children.get(pos);
+        }
+
+        Entry::Vacant(VacantEntry {
+            map: self,
+            key,
+            path,
+            index: 0,
+        })
+    }
+}
+
+impl<'a, K: Ord, V> Entry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Vacant(entry) => entry.key(),
+            Entry::Occupied(entry) => entry.key(),
+        }
+    }
+
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Vacant(entry) => entry.insert(default()),
+            Entry::Occupied(entry) => entry.into_mut(),
+        }
+    }
+
+    pub fn or_default(self) -> &'a mut V
+    where
+        V: Default,
+    {
+        self.or_insert_with(V::default)
+    }
+
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            vacant => vacant,
+        }
+    }
+}
+
+impl<'a, K: Ord, V> VacantEntry<'a, K, V> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /*the key goes into the leaf the descent ended at, then overfull nodes are split
+     * bottom up along the path, following the new entry wherever a split moves it
+     */
+    pub fn insert(self, value: V) -> &'a mut V {
+        let VacantEntry {
+            map,
+            key,
+            path,
+            index,
+        } = self;
+        let min_degree = map.min_degree;
+        let max_keys = 2 * min_degree - 1;
+        let root = map
+            .root
+            .get_or_insert_with(|| BTreeNode::new(min_degree, true));
+
+        let leaf = root.descend_mut(&path);
+        leaf.keys.insert(index, key);
+        leaf.values.insert(index, value);
+
+        let mut at = (path.clone(), index);
+        for depth in (1..=path.len()).rev() {
+            let parent = root.descend_mut(&path[..depth - 1]);
+            let child = path[depth - 1];
+            if parent.children[child].keys.len() <= max_keys {
+                break;
+            }
+            parent.split_child_at(child, min_degree - 1);
+            follow_split(&mut at, depth, child, min_degree - 1);
+        }
+
+        if root.keys.len() > max_keys {
+            let old_root = std::mem::replace(root, BTreeNode::new(min_degree, false));
+            root.children.push(old_root);
+            root.split_child_at(0, min_degree - 1);
+            at.0.insert(0, 0);
+            follow_split(&mut at, 1, 0, min_degree - 1);
+        }
+
+        map.len += 1;
+        map.validate();
+        let (path, index) = at;
+        &mut map.root.as_mut().unwrap().descend_mut(&path).values[index]
+    }
+}
+
+/*the node `depth` steps down the path, child `child` of its parent, was just split around
+ * its key `split`: move the tracked entry position along if the split touched it
+ */
+fn follow_split(at: &mut (Vec<usize>, usize), depth: usize, child: usize, split: usize) {
+    let (path, index) = at;
+    if path.len() == depth {
+        if *index == split {
+            path.pop();
+            *index = child;
+        } else if *index > split {
+            path[depth - 1] = child + 1;
+            *index -= split + 1;
+        }
+    } else if path[depth] > split {
+        path[depth - 1] = child + 1;
+        path[depth] -= split + 1;
+    }
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+    fn node(&self) -> &BTreeNode<K, V> {
+        self.map.root.as_ref().unwrap().descend(&self.path)
+    }
+
+    fn node_mut(&mut self) -> &mut BTreeNode<K, V> {
+        self.map.root.as_mut().unwrap().descend_mut(&self.path)
+    }
+
+    pub fn key(&self) -> &K {
+        &self.node().keys[self.index]
+    }
+
+    pub fn get(&self) -> &V {
+        &self.node().values[self.index]
+    }
+
+    pub fn get_mut(&mut self) -> &mut V {
+        let index = self.index;
+        &mut self.node_mut().values[index]
+    }
+
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self
+            .map
+            .root
+            .as_mut()
+            .unwrap()
+            .descend_mut(&self.path)
+            .values[self.index]
+    }
+
+    pub fn insert(&mut self, value: V) -> V {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    pub fn remove_entry(self) -> (K, V) {
+        let removed = self
+            .map
+            .root
+            .as_mut()
+            .unwrap()
+            .remove_at(&self.path, self.index);
+        self.map.len -= 1;
+        self.map.fix_top();
+        self.map.validate();
+        removed
+    }
+}
@@ -0,0 +1,294 @@
+use core::borrow::Borrow;
+use core::ops::{Bound, RangeBounds};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/*fixed capacity map that never allocates
+ * it only uses core, but the crate itself still builds against std, there is no no_std
+ * build of it yet
+ * all storage is an inline array of MAX slots, the occupied prefix is kept sorted and
+ * searched by bisection, effectively one B-tree node that never splits,
+ * which at embedded sizes shifts fewer bytes than a tree of small nodes would chase
+ * what a new key does once every slot is taken is up to its OverflowPolicy
+ */
+#[derive(Debug)]
+pub struct FixedBTreeMap<K, V, const MAX: usize> {
+    entries: [Option<(K, V)>; MAX],
+    len: usize,
+    policy: OverflowPolicy,
+    /*stamp of the last get or insert of each slot, moves with its entry, only for EvictLru
+     * atomics so get can stamp through &self and the map stays Sync, plain loads and stores
+     * are all there is on small targets
+     */
+    last_used: [AtomicUsize; MAX],
+    clock: AtomicUsize,
+}
+
+/*what insert does with a new key when the map is full
+ * the evicting policies drop the evicted entry and make room, unless the new key is the
+ * one they would evict (smaller than every key for EvictSmallest, larger for EvictLargest),
+ * that one is rejected with Full like under Reject
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /*fail with Full*/
+    #[default]
+    Reject,
+    /*keep the largest keys, e.g. a window of the latest sequence numbers*/
+    EvictSmallest,
+    /*keep the smallest keys, e.g. the earliest deadlines*/
+    EvictLargest,
+    /*drop the entry that went longest without a get or insert*/
+    EvictLru,
+}
+
+/*returned by insert when every slot is taken, hands the rejected entry back*/
+#[derive(Debug)]
+pub struct Full<K, V> {
+    pub key: K,
+    pub value: V,
+}
+
+impl<K: Ord, V, const MAX: usize> FixedBTreeMap<K, V, MAX> {
+    pub const fn new() -> Self {
+        Self::with_policy(OverflowPolicy::Reject)
+    }
+
+    pub const fn with_policy(policy: OverflowPolicy) -> Self {
+        FixedBTreeMap {
+            entries: [const { None }; MAX],
+            len: 0,
+            policy,
+            last_used: [const { AtomicUsize::new(0) }; MAX],
+            clock: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn policy(&self) -> OverflowPolicy {
+        self.policy
+    }
+
+    /*concurrent gets may hand out the same stamp, that only blurs the LRU order*/
+    fn touch(&self, index: usize) {
+        let now = self.clock.load(Ordering::Relaxed).wrapping_add(1);
+        self.clock.store(now, Ordering::Relaxed);
+        self.last_used[index].store(now, Ordering::Relaxed);
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn position<Q>(&self, key: &Q) -> Result<usize, usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.entries[..self.len].binary_search_by(|entry| Self::key_of(entry).borrow().cmp(key))
+    }
+
+    fn key_of(entry: &Option<(K, V)>) -> &K {
+        match entry {
+            Some((key, _)) => key,
+            None => unreachable!("slots before len are always occupied"),
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.position(key).ok()?;
+        self.touch(index);
+        self.entries[index].as_ref().map(|(_, value)| value)
+    }
+
+    /*replaces and returns the old value if the key exists,
+     * fails only when a new key doesn't fit and the policy doesn't make room for it
+     */
+    pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, Full<K, V>> {
+        let mut index = match self.position(&key) {
+            Ok(index) => {
+                self.touch(index);
+                let (_, old) = self.entries[index].as_mut().unwrap();
+                return Ok(Some(core::mem::replace(old, value)));
+            }
+            Err(index) => index,
+        };
+
+        if self.len == MAX {
+            let victim = match self.policy {
+                OverflowPolicy::EvictSmallest if index > 0 => 0,
+                OverflowPolicy::EvictLargest if index < MAX => MAX - 1,
+                OverflowPolicy::EvictLru if MAX > 0 => (0..MAX)
+                    .min_by_key(|&slot| self.last_used[slot].load(Ordering::Relaxed))
+                    .unwrap(),
+                _ => return Err(Full { key, value }),
+            };
+            self.take(victim);
+            if victim < index {
+                index -= 1;
+            }
+        }
+
+        self.entries[index..=self.len].rotate_right(1);
+        self.last_used[index..=self.len].rotate_right(1);
+        self.entries[index] = Some((key, value));
+        self.len += 1;
+        self.touch(index);
+        Ok(None)
+    }
+
+    /*empty slot `index` and close the gap*/
+    fn take(&mut self, index: usize) -> Option<(K, V)> {
+        let entry = self.entries[index].take();
+        self.entries[index..self.len].rotate_left(1);
+        self.last_used[index..self.len].rotate_left(1);
+        self.len -= 1;
+        entry
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self.position(key).ok()?;
+        self.take(index).map(|(_, value)| value)
+    }
+
+    /*doesn't count as a use for EvictLru*/
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> impl Iterator<Item = (&K, &V)> + '_ {
+        let occupied = &self.entries[..self.len];
+        let start = occupied.partition_point(|entry| match range.start_bound() {
+            Bound::Included(start) => Self::key_of(entry) < start,
+            Bound::Excluded(start) => Self::key_of(entry) <= start,
+            Bound::Unbounded => false,
+        });
+        let end = occupied.partition_point(|entry| match range.end_bound() {
+            Bound::Included(end) => Self::key_of(entry) <= end,
+            Bound::Excluded(end) => Self::key_of(entry) < end,
+            Bound::Unbounded => true,
+        });
+
+        occupied[start..end.max(start)]
+            .iter()
+            .flatten()
+            .map(|(key, value)| (key, value))
+    }
+}
+
+impl<K: Ord, V, const MAX: usize> Default for FixedBTreeMap<K, V, MAX> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys<const MAX: usize>(map: &FixedBTreeMap<u32, char, MAX>) -> Vec<u32> {
+        map.range(..).map(|(key, _)| *key).collect()
+    }
+
+    #[test]
+    fn inserts_keep_the_keys_sorted() {
+        let mut map: FixedBTreeMap<u32, char, 8> = FixedBTreeMap::new();
+        for (key, value) in [(5, 'e'), (1, 'a'), (3, 'c'), (4, 'd'), (2, 'b')] {
+            assert!(map.insert(key, value).unwrap().is_none());
+        }
+        assert_eq!(keys(&map), [1, 2, 3, 4, 5]);
+        assert_eq!(map.insert(3, 'C').unwrap(), Some('c'));
+        assert_eq!(map.get(&3), Some(&'C'));
+        assert_eq!(map.len(), 5);
+    }
+
+    #[test]
+    fn full_map_hands_the_rejected_entry_back() {
+        let mut map: FixedBTreeMap<u32, char, 3> = FixedBTreeMap::new();
+        for key in 0..3 {
+            map.insert(key, 'x').unwrap();
+        }
+        let Err(Full { key, value }) = map.insert(7, 'y') else {
+            panic!("insert into a full map succeeded");
+        };
+        assert_eq!((key, value), (7, 'y'));
+        assert_eq!(keys(&map), [0, 1, 2]);
+
+        /*overwriting an existing key still works when full*/
+        assert_eq!(map.insert(1, 'z').unwrap(), Some('x'));
+    }
+
+    #[test]
+    fn remove_closes_the_gap() {
+        let mut map: FixedBTreeMap<u32, char, 4> = FixedBTreeMap::new();
+        for key in 0..4 {
+            map.insert(key, 'x').unwrap();
+        }
+        assert_eq!(map.remove(&1), Some('x'));
+        assert_eq!(map.remove(&1), None);
+        assert_eq!(keys(&map), [0, 2, 3]);
+        map.insert(9, 'y').unwrap();
+        assert_eq!(keys(&map), [0, 2, 3, 9]);
+    }
+
+    #[test]
+    fn range_bounds() {
+        let mut map: FixedBTreeMap<u32, char, 16> = FixedBTreeMap::new();
+        for key in (0..20).step_by(2) {
+            map.insert(key, 'x').unwrap();
+        }
+        let range = |r: (Bound<u32>, Bound<u32>)| -> Vec<u32> {
+            map.range(r).map(|(key, _)| *key).collect()
+        };
+        assert_eq!(range((Bound::Included(4), Bound::Excluded(10))), [4, 6, 8]);
+        assert_eq!(range((Bound::Excluded(4), Bound::Included(10))), [6, 8, 10]);
+        assert_eq!(range((Bound::Unbounded, Bound::Excluded(3))), [0, 2]);
+        assert_eq!(range((Bound::Included(15), Bound::Unbounded)), [16, 18]);
+        assert!(range((Bound::Included(10), Bound::Excluded(4))).is_empty());
+    }
+
+    fn full(policy: OverflowPolicy) -> FixedBTreeMap<u32, char, 3> {
+        let mut map = FixedBTreeMap::with_policy(policy);
+        for key in [10, 20, 30] {
+            map.insert(key, 'x').unwrap();
+        }
+        map
+    }
+
+    #[test]
+    fn evict_smallest_keeps_the_largest_keys() {
+        let mut map = full(OverflowPolicy::EvictSmallest);
+        map.insert(25, 'y').unwrap();
+        assert_eq!(keys(&map), [20, 25, 30]);
+        /*a key below all of them would be evicted right away*/
+        assert!(map.insert(5, 'y').is_err());
+        assert_eq!(keys(&map), [20, 25, 30]);
+    }
+
+    #[test]
+    fn evict_largest_keeps_the_smallest_keys() {
+        let mut map = full(OverflowPolicy::EvictLargest);
+        map.insert(15, 'y').unwrap();
+        assert_eq!(keys(&map), [10, 15, 20]);
+        assert!(map.insert(40, 'y').is_err());
+        assert_eq!(keys(&map), [10, 15, 20]);
+    }
+
+    #[test]
+    fn evict_lru_drops_the_least_recently_used_key() {
+        let mut map = full(OverflowPolicy::EvictLru);
+        map.insert(40, 'y').unwrap();
+        assert_eq!(keys(&map), [20, 30, 40]);
+
+        /*a get refreshes 20, so 30 is the oldest now*/
+        assert_eq!(map.get(&20), Some(&'x'));
+        map.insert(5, 'y').unwrap();
+        assert_eq!(keys(&map), [5, 20, 40]);
+    }
+}
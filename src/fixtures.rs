@@ -0,0 +1,96 @@
+/*key sets for benchmarks, so runs comparing degrees or modes (ours or downstream) measure
+ * the same inputs: every generator is deterministic for a given seed and needs no deps
+ */
+
+/*dataset sizes the comparisons are usually run at*/
+pub const SMALL: usize = 1_000;
+pub const MEDIUM: usize = 100_000;
+pub const LARGE: usize = 1_000_000;
+pub const SIZES: [usize; 3] = [SMALL, MEDIUM, LARGE];
+
+/*xorshift64, fast and good enough to shuffle benchmark keys, not for anything else*/
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    /*a zero state would stay zero forever, so it's nudged*/
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /*uniform in 0..1*/
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /*in 0..bound, the modulo bias doesn't matter at benchmark sizes*/
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound.max(1)
+    }
+}
+
+/*0, 1, 2, ... the best case for time series trees and the worst for a naive split*/
+pub fn sequential(n: usize) -> Vec<u64> {
+    (0..n as u64).collect()
+}
+
+/*n distinct keys in random order: a shuffled sequential, so sizes match exactly*/
+pub fn random(n: usize, seed: u64) -> Vec<u64> {
+    let mut rng = Rng::new(seed);
+    let mut keys = sequential(n);
+    for i in (1..keys.len()).rev() {
+        keys.swap(i, rng.below(i as u64 + 1) as usize);
+    }
+    keys
+}
+
+/*n draws (with repeats) from 0..universe where key k comes up in proportion to
+ * 1 / (k + 1)^exponent, i.e. a few hot keys and a long tail, exponent 1.0 is the classic
+ */
+pub fn zipfian(n: usize, universe: usize, exponent: f64, seed: u64) -> Vec<u64> {
+    let mut rng = Rng::new(seed);
+    let mut cumulative = Vec::with_capacity(universe);
+    let mut total = 0.0;
+    for rank in 0..universe {
+        total += 1.0 / ((rank + 1) as f64).powf(exponent);
+        cumulative.push(total);
+    }
+
+    (0..n)
+        .map(|_| {
+            let target = rng.next_f64() * total;
+            cumulative
+                .partition_point(|&weight| weight <= target)
+                .min(universe.saturating_sub(1)) as u64
+        })
+        .collect()
+}
+
+/*n distinct strings sharing one of `clusters` prefixes, like table or tenant ids in front
+ * of a row id: long common prefixes make comparisons expensive and keys bunch up
+ */
+pub fn clustered_strings(n: usize, clusters: usize, seed: u64) -> Vec<String> {
+    let mut rng = Rng::new(seed);
+    let clusters = clusters.max(1);
+    let prefixes: Vec<String> = (0..clusters)
+        .map(|cluster| format!("tenant-{:04}/orders/", cluster))
+        .collect();
+
+    random(n, seed)
+        .into_iter()
+        .map(|id| {
+            format!(
+                "{}{:012}",
+                prefixes[rng.below(clusters as u64) as usize],
+                id
+            )
+        })
+        .collect()
+}
@@ -0,0 +1,144 @@
+use std::borrow::Borrow;
+use std::fmt::Debug;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/*read only map over a sorted static slice, so a lookup table costs nothing to build:
+ * it's a const and lives in the binary, lookups binary search the slice
+ * the entries have to be sorted by key without duplicates, freeze_to_rust_source writes
+ * them that way, nothing is checked at compile time
+ */
+#[derive(Debug, Clone, Copy)]
+pub struct FrozenMap<K: 'static, V: 'static> {
+    entries: &'static [(K, V)],
+}
+
+impl<K: Ord, V> FrozenMap<K, V> {
+    pub const fn new(entries: &'static [(K, V)]) -> Self {
+        FrozenMap { entries }
+    }
+
+    pub fn get_key_value<Q>(&self, key: &Q) -> Option<(&'static K, &'static V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let entries = self.entries;
+        entries
+            .binary_search_by(|(k, _)| k.borrow().cmp(key))
+            .ok()
+            .map(|pos| (&entries[pos].0, &entries[pos].1))
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&'static V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_key_value(key).map(|(_, value)| value)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get_key_value(key).is_some()
+    }
+
+    pub fn first_key_value(&self) -> Option<(&'static K, &'static V)> {
+        self.entries.first().map(|(key, value)| (key, value))
+    }
+
+    pub fn last_key_value(&self) -> Option<(&'static K, &'static V)> {
+        self.entries.last().map(|(key, value)| (key, value))
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = (&'static K, &'static V)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+
+    pub const fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+/*for build scripts: write `entries` as a Rust slice expression, sorted by key with the last
+ * value of a repeated key kept (like collect), to be wrapped at compile time with
+ *     static TABLE: FrozenMap<&str, u32> =
+ *         FrozenMap::new(include!(concat!(env!("OUT_DIR"), "/table.rs")));
+ * keys and values are written with their Debug output, so that has to be a valid Rust
+ * expression of the type in the static, supported are:
+ *   integers, bools, chars, and finite floats
+ *   strings, Debug quotes them with Rust escapes, a String comes out as a literal and goes
+ *   into a FrozenMap<&str, _>
+ *   tuples and Options of the above
+ * anything else is not, the file is written but fails to compile or means something else:
+ * NaN and infinite floats print as NaN and inf, a Vec prints as an array literal, derived
+ * Debug of a struct or enum leaves out the path and prints private fields
+ */
+pub fn freeze_to_rust_source<K, V, I, P>(entries: I, path: P) -> io::Result<()>
+where
+    K: Ord + Debug,
+    V: Debug,
+    I: IntoIterator<Item = (K, V)>,
+    P: AsRef<Path>,
+{
+    let mut source = String::from("&[\n");
+    for (key, value) in crate::sort_entries(entries.into_iter().collect()) {
+        source.push_str(&format!("    ({:?}, {:?}),\n", key, value));
+    }
+    source.push_str("]\n");
+    fs::write(path, source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /*the same text freeze_to_rust_source has to write below, so it compiles*/
+    static TABLE: FrozenMap<(&str, i8), Option<char>> = FrozenMap::new(&[
+        (("", -1), None),
+        (("a\0\"b\\", 0), Some('\'')),
+        (("a\n", 3), Some('é')),
+        (("b", -128), Some('\u{301}')),
+    ]);
+
+    #[test]
+    fn generated_source_is_sorted_rust() {
+        let path = std::env::temp_dir().join(format!("btreemap-frozen-{}", std::process::id()));
+        freeze_to_rust_source(
+            [
+                (("b", i8::MIN), Some('\u{301}')),
+                (("a\n", 3), Some('x')),
+                (("a\0\"b\\", 0), Some('\'')),
+                (("", -1), None),
+                (("a\n", 3), Some('é')),
+            ],
+            &path,
+        )
+        .unwrap();
+        let source = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            source,
+            concat!(
+                "&[\n",
+                "    ((\"\", -1), None),\n",
+                "    ((\"a\\0\\\"b\\\\\", 0), Some('\\'')),\n",
+                "    ((\"a\\n\", 3), Some('é')),\n",
+                "    ((\"b\", -128), Some('\\u{301}')),\n",
+                "]\n",
+            )
+        );
+        assert_eq!(TABLE.len(), 4);
+        assert_eq!(TABLE.get(&("a\0\"b\\", 0)), Some(&Some('\'')));
+        assert_eq!(TABLE.get(&("a\n", 3)), Some(&Some('é')));
+    }
+}
@@ -0,0 +1,181 @@
+use crate::BTreeMap;
+use std::borrow::Borrow;
+
+/*reference to one entry of a HandleMap, cheap to keep around in another structure
+ * it holds a copy of the key plus the identity the entry got when it was inserted,
+ * so a key that was removed and inserted again doesn't revive old handles
+ */
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct KeyHandle<K> {
+    key: K,
+    entry: u64,
+    generation: u64,
+}
+
+impl<K> KeyHandle<K> {
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+}
+
+/*why get_by_handle turned a handle down*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StaleHandle {
+    /*the entry was removed, its key may be back as a different entry*/
+    Removed,
+    /*the map was cleared since the handle was handed out*/
+    Cleared,
+}
+
+impl std::fmt::Display for StaleHandle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StaleHandle::Removed => write!(f, "stale handle: the entry was removed"),
+            StaleHandle::Cleared => write!(f, "stale handle: the map was cleared"),
+        }
+    }
+}
+
+impl std::error::Error for StaleHandle {}
+
+/*map that hands out KeyHandles for layered systems (indexes, caches) to point at its entries
+ * every new entry gets the next value of a counter, clear starts a new generation so
+ * handles from before it fail without a lookup, overwriting a value keeps the entry
+ */
+#[derive(Debug)]
+pub struct HandleMap<K, V> {
+    map: BTreeMap<K, (V, u64)>,
+    next_entry: u64,
+    generation: u64,
+}
+
+impl<K: Ord + Clone, V> HandleMap<K, V> {
+    pub fn new(min_degree: usize) -> Self {
+        HandleMap {
+            map: BTreeMap::new(min_degree),
+            next_entry: 0,
+            generation: 0,
+        }
+    }
+
+    /*store `value` under `key` and return a handle to the entry, an existing entry only
+     * gets the new value and keeps its identity, so handles to it stay valid
+     */
+    pub fn insert(&mut self, key: K, value: V) -> KeyHandle<K> {
+        let entry = match self.map.get_mut(&key) {
+            Some(stored) => {
+                stored.0 = value;
+                stored.1
+            }
+            None => {
+                let entry = self.next_entry;
+                self.next_entry += 1;
+                self.map.insert(key.clone(), (value, entry));
+                entry
+            }
+        };
+        KeyHandle {
+            key,
+            entry,
+            generation: self.generation,
+        }
+    }
+
+    /*a handle to the entry under `key`, if there is one*/
+    pub fn handle(&self, key: &K) -> Option<KeyHandle<K>> {
+        let (_, entry) = self.map.get(key)?;
+        Some(KeyHandle {
+            key: key.clone(),
+            entry: *entry,
+            generation: self.generation,
+        })
+    }
+
+    pub fn get_by_handle(&self, handle: &KeyHandle<K>) -> Result<&V, StaleHandle> {
+        self.check(handle)?;
+        match self.map.get(&handle.key) {
+            Some((value, entry)) if *entry == handle.entry => Ok(value),
+            _ => Err(StaleHandle::Removed),
+        }
+    }
+
+    pub fn get_by_handle_mut(&mut self, handle: &KeyHandle<K>) -> Result<&mut V, StaleHandle> {
+        self.check(handle)?;
+        match self.map.get_mut(&handle.key) {
+            Some((value, entry)) if *entry == handle.entry => Ok(value),
+            _ => Err(StaleHandle::Removed),
+        }
+    }
+
+    fn check(&self, handle: &KeyHandle<K>) -> Result<(), StaleHandle> {
+        if handle.generation == self.generation {
+            Ok(())
+        } else {
+            Err(StaleHandle::Cleared)
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.get(key).map(|(value, _)| value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.remove(key).map(|(value, _)| value)
+    }
+
+    /*drop every entry, every handle handed out so far goes stale*/
+    pub fn clear(&mut self) {
+        self.map.clear();
+        self.generation += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reinserted_key_rejects_the_old_handle() {
+        let mut map = HandleMap::new(2);
+        let old = map.insert(7, "first");
+        assert_eq!(map.get_by_handle(&old), Ok(&"first"));
+
+        assert_eq!(map.remove(&7), Some("first"));
+        assert_eq!(map.get_by_handle(&old), Err(StaleHandle::Removed));
+
+        let new = map.insert(7, "second");
+        assert_eq!(map.get_by_handle(&old), Err(StaleHandle::Removed));
+        assert_eq!(map.get_by_handle_mut(&old), Err(StaleHandle::Removed));
+        assert_eq!(map.get_by_handle(&new), Ok(&"second"));
+        assert_eq!(map.handle(&7), Some(new));
+    }
+
+    #[test]
+    fn overwrite_keeps_the_handle_and_clear_drops_it() {
+        let mut map = HandleMap::new(2);
+        let handle = map.insert(1, 10);
+        assert_eq!(map.insert(1, 11), handle);
+        *map.get_by_handle_mut(&handle).unwrap() += 1;
+        assert_eq!(map.get(&1), Some(&12));
+
+        map.clear();
+        map.insert(1, 13);
+        assert_eq!(map.get_by_handle(&handle), Err(StaleHandle::Cleared));
+    }
+}
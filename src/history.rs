@@ -0,0 +1,83 @@
+use crate::BTreeMap;
+use std::borrow::Borrow;
+use std::collections::VecDeque;
+
+/*map that remembers the last `depth` values every key held before its current one,
+ * e.g. for config stores, without versions or a versioned engine behind it
+ * an overwrite pushes the old value onto the key's ring and drops the oldest one once
+ * the ring is full, remove drops the key together with its history
+ */
+#[derive(Debug)]
+pub struct HistoryMap<K, V> {
+    map: BTreeMap<K, (V, VecDeque<V>)>,
+    depth: usize,
+}
+
+impl<K: Ord, V> HistoryMap<K, V> {
+    pub fn new(min_degree: usize, depth: usize) -> Self {
+        HistoryMap {
+            map: BTreeMap::new(min_degree),
+            depth,
+        }
+    }
+
+    /*returns whether the key was already present, its old value is in history() now*/
+    pub fn insert(&mut self, key: K, value: V) -> bool {
+        let Some((current, history)) = self.map.get_mut(&key) else {
+            self.map.insert(key, (value, VecDeque::new()));
+            return false;
+        };
+
+        let old = std::mem::replace(current, value);
+        if self.depth > 0 {
+            if history.len() == self.depth {
+                history.pop_back();
+            }
+            history.push_front(old);
+        }
+        true
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.get(key).map(|(value, _)| value)
+    }
+
+    /*the values `key` held before its current one, the most recent first, empty for a
+     * missing key, nth(1) is the value before the last two updates
+     */
+    pub fn history<Q>(&self, key: &Q) -> impl Iterator<Item = &V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map
+            .get(key)
+            .into_iter()
+            .flat_map(|(_, history)| history.iter())
+    }
+
+    /*the current value, its history is dropped with it*/
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.map.remove(key).map(|(value, _)| value)
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
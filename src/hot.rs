@@ -0,0 +1,182 @@
+use crate::{BTreeMap, Clock, SystemClock};
+use std::borrow::Borrow;
+use std::time::{Duration, Instant};
+
+/*a bucket of the keyspace and its smoothed rates, start is inclusive and end exclusive,
+ * None on the open ends
+ */
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotRange<K> {
+    pub start: Option<K>,
+    pub end: Option<K>,
+    pub reads_per_sec: f64,
+    pub writes_per_sec: f64,
+    pub splits_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Bucket {
+    reads: u64,
+    writes: u64,
+    splits: u64,
+    read_rate: f64,
+    write_rate: f64,
+    split_rate: f64,
+}
+
+/*wraps a map and counts reads, writes and node splits per key range bucket
+ * counts only turn into rates on sample(), call it periodically (e.g. from a Maintenance
+ * task), each sample moves the rates towards what was seen since the previous one as an
+ * exponentially weighted moving average, after `half_life` old traffic weighs half
+ */
+#[derive(Debug)]
+pub struct HotRangeTracker<K, V> {
+    map: BTreeMap<K, V>,
+    boundaries: Vec<K>,
+    buckets: Vec<Bucket>,
+    half_life: Duration,
+    last_sample: Instant,
+    clock: Box<dyn Clock>,
+}
+
+impl<K: Ord + Clone, V> HotRangeTracker<K, V> {
+    /*`boundaries` (sorted) cut the keyspace into boundaries.len() + 1 buckets*/
+    pub fn new(map: BTreeMap<K, V>, boundaries: Vec<K>, half_life: Duration) -> Self {
+        Self::with_clock(map, boundaries, half_life, SystemClock)
+    }
+
+    /*rates are per second of `clock`, so with a ManualClock they come out the same on every run*/
+    pub fn with_clock<C: Clock + 'static>(
+        map: BTreeMap<K, V>,
+        mut boundaries: Vec<K>,
+        half_life: Duration,
+        clock: C,
+    ) -> Self {
+        boundaries.sort();
+        boundaries.dedup();
+        HotRangeTracker {
+            map,
+            buckets: vec![Bucket::default(); boundaries.len() + 1],
+            boundaries,
+            half_life,
+            last_sample: clock.now(),
+            clock: Box::new(clock),
+        }
+    }
+
+    pub fn map(&self) -> &BTreeMap<K, V> {
+        &self.map
+    }
+
+    pub fn into_inner(self) -> BTreeMap<K, V> {
+        self.map
+    }
+
+    fn bucket<Q>(&mut self, key: &Q) -> &mut Bucket
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let index = self
+            .boundaries
+            .partition_point(|boundary| boundary.borrow() <= key);
+        &mut self.buckets[index]
+    }
+
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.bucket(key).reads += 1;
+        self.map.get(key)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        let splits = self.map.full_nodes_on_path(&key) as u64;
+        let bucket = self.bucket(&key);
+        bucket.writes += 1;
+        bucket.splits += splits;
+        self.map.insert(key, value)
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.bucket(key).writes += 1;
+        self.map.remove(key)
+    }
+
+    /*fold the counts since the last sample into the rates and start counting afresh*/
+    pub fn sample(&mut self) {
+        let now = self.clock.now();
+        let elapsed = now.duration_since(self.last_sample).as_secs_f64();
+        if elapsed <= 0.0 {
+            return;
+        }
+        self.last_sample = now;
+
+        let weight = 1.0 - 0.5f64.powf(elapsed / self.half_life.as_secs_f64());
+        for bucket in &mut self.buckets {
+            bucket.read_rate += weight * (bucket.reads as f64 / elapsed - bucket.read_rate);
+            bucket.write_rate += weight * (bucket.writes as f64 / elapsed - bucket.write_rate);
+            bucket.split_rate += weight * (bucket.splits as f64 / elapsed - bucket.split_rate);
+            bucket.reads = 0;
+            bucket.writes = 0;
+            bucket.splits = 0;
+        }
+    }
+
+    /*the `n` buckets with the most reads and writes per second, busiest first*/
+    pub fn hot_ranges(&self, n: usize) -> Vec<HotRange<K>> {
+        let mut order: Vec<usize> = (0..self.buckets.len()).collect();
+        let traffic =
+            |index: &usize| self.buckets[*index].read_rate + self.buckets[*index].write_rate;
+        order.sort_by(|a, b| traffic(b).total_cmp(&traffic(a)));
+
+        order
+            .into_iter()
+            .take(n)
+            .map(|index| {
+                let bucket = &self.buckets[index];
+                HotRange {
+                    start: index
+                        .checked_sub(1)
+                        .map(|start| self.boundaries[start].clone()),
+                    end: self.boundaries.get(index).cloned(),
+                    reads_per_sec: bucket.read_rate,
+                    writes_per_sec: bucket.write_rate,
+                    splits_per_sec: bucket.split_rate,
+                }
+            })
+            .collect()
+    }
+}
+
+impl<K: Ord, V> BTreeMap<K, V> {
+    /*inserting a new key splits every full node on its way down (the top down insert
+     * splits ahead, the time series path splits the full right spine), an existing key
+     * found on the way stops the count
+     */
+    pub(crate) fn full_nodes_on_path(&self, key: &K) -> usize {
+        let max_keys = 2 * self.min_degree - 1;
+        let mut full = 0;
+        let mut node = self.root.as_ref();
+        while let Some(current) = node {
+            let pos = current
+                .keys
+                .iter()
+                .position(|k| k >= key)
+                .unwrap_or(current.keys.len());
+            if pos < current.keys.len() && current.keys[pos] == *key {
+                break;
+            }
+
+            full += usize::from(current.keys.len() == max_keys);
+            node = current.children.get(pos);
+        }
+        full
+    }
+}
@@ -0,0 +1,418 @@
+use crate::node::BTreeNode;
+use crate::BTreeMap;
+use std::ops::Bound;
+
+/*in order traversal with an explicit stack
+ * each frame is a node and the index of its next entry,
+ * the subtree left of that entry is always pushed on top of it
+ * the root is kept so seek can rebuild the stack after the last entry
+ */
+pub struct Iter<'a, K, V> {
+    pub(crate) root: Option<&'a BTreeNode<K, V>>,
+    pub(crate) stack: Vec<(&'a BTreeNode<K, V>, usize)>,
+}
+
+impl<'a, K: Ord, V> Iter<'a, K, V> {
+    /*rebuild the stack for the first entry at or after `start`: one descent from the root,
+     * every node on the way is pushed with the position `start` falls at
+     */
+    pub(crate) fn descend_to(&mut self, start: Bound<&K>) {
+        self.stack.clear();
+        let mut node = self.root;
+        while let Some(current) = node {
+            let pos = current
+                .keys
+                .iter()
+                .position(|k| match start {
+                    Bound::Included(start) => k >= start,
+                    Bound::Excluded(start) => k > start,
+                    Bound::Unbounded => true,
+                })
+                .unwrap_or(current.keys.len());
+            self.stack.push((current, pos));
+            node = current.children.get(pos);
+        }
+    }
+
+    /*continue from the first entry >= `key`, forwards or backwards, in O(height) and
+     * without a new iterator, e.g. for skip scans and leapfrog joins
+     */
+    pub fn seek(&mut self, key: &K) {
+        self.descend_to(Bound::Included(key));
+    }
+}
+
+impl<'a, K, V> Iter<'a, K, V> {
+    pub(crate) fn push_left_edge(&mut self, mut node: &'a BTreeNode<K, V>) {
+        loop {
+            self.stack.push((node, 0));
+            match node.children.first() {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, next)) = self.stack.last_mut() {
+            let node = *node;
+            if *next == node.keys.len() {
+                self.stack.pop();
+                continue;
+            }
+
+            let index = *next;
+            *next += 1;
+            if let Some(child) = node.children.get(index + 1) {
+                self.push_left_edge(child);
+            }
+            return Some((&node.keys[index], &node.values[index]));
+        }
+        None
+    }
+}
+
+pub struct Range<'a, K, V> {
+    pub(crate) iter: Iter<'a, K, V>,
+    pub(crate) start: Bound<K>,
+    pub(crate) end: Bound<K>,
+}
+
+impl<'a, K: Ord, V> Range<'a, K, V> {
+    /*like Iter::seek, but never leaves the range: a key before its start seeks to the
+     * start, one past its end ends the range
+     */
+    pub fn seek(&mut self, key: &K) {
+        let start = match &self.start {
+            Bound::Included(start) if key < start => Bound::Included(start),
+            Bound::Excluded(start) if key <= start => Bound::Excluded(start),
+            _ => Bound::Included(key),
+        };
+        self.iter.descend_to(start);
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next()?;
+        let in_range = match &self.end {
+            Bound::Included(end) => key <= end,
+            Bound::Excluded(end) => key < end,
+            Bound::Unbounded => true,
+        };
+
+        if in_range {
+            Some((key, value))
+        } else {
+            self.iter.stack.clear();
+            None
+        }
+    }
+}
+
+/*consuming in order traversal, same shape as Iter but every frame owns
+ * what is left of its node: the keys and values still to be yielded and
+ * the children right of them
+ */
+pub struct IntoIter<K, V> {
+    stack: Vec<IntoIterFrame<K, V>>,
+}
+
+struct IntoIterFrame<K, V> {
+    keys: std::vec::IntoIter<K>,
+    values: std::vec::IntoIter<V>,
+    children: std::vec::IntoIter<BTreeNode<K, V>>,
+}
+
+impl<K, V> IntoIter<K, V> {
+    fn push_left_edge(&mut self, mut node: BTreeNode<K, V>) {
+        loop {
+            let mut children = node.children.into_iter();
+            let first = children.next();
+            self.stack.push(IntoIterFrame {
+                keys: node.keys.into_iter(),
+                values: node.values.into_iter(),
+                children,
+            });
+            match first {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+    }
+}
+
+impl<K, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(frame) = self.stack.last_mut() {
+            let Some(key) = frame.keys.next() else {
+                self.stack.pop();
+                continue;
+            };
+
+            let value = frame.values.next().unwrap();
+            if let Some(child) = frame.children.next() {
+                self.push_left_edge(child);
+            }
+            return Some((key, value));
+        }
+        None
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a BTreeMap<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<K, V> IntoIterator for BTreeMap<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut iter = IntoIter { stack: Vec::new() };
+        if let Some(root) = self.root {
+            iter.push_left_edge(root);
+        }
+        iter
+    }
+}
+
+/*reverse in order traversal with an explicit stack
+ * each frame is a node and the number of its entries not yet yielded,
+ * the subtree left of the next entry is always pushed on top of it
+ */
+pub struct IterRev<'a, K, V> {
+    pub(crate) stack: Vec<(&'a BTreeNode<K, V>, usize)>,
+}
+
+impl<'a, K, V> IterRev<'a, K, V> {
+    pub(crate) fn push_right_edge(&mut self, mut node: &'a BTreeNode<K, V>) {
+        loop {
+            self.stack.push((node, node.keys.len()));
+            match node.children.last() {
+                Some(child) => node = child,
+                None => break,
+            }
+        }
+    }
+}
+
+impl<'a, K, V> Iterator for IterRev<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, remaining)) = self.stack.last_mut() {
+            let node = *node;
+            if *remaining == 0 {
+                self.stack.pop();
+                continue;
+            }
+
+            *remaining -= 1;
+            let index = *remaining;
+            if let Some(child) = node.children.get(index) {
+                self.push_right_edge(child);
+            }
+            return Some((&node.keys[index], &node.values[index]));
+        }
+        None
+    }
+}
+
+pub struct RangeRev<'a, K, V> {
+    pub(crate) iter: IterRev<'a, K, V>,
+    pub(crate) start: Bound<K>,
+}
+
+impl<'a, K: Ord, V> Iterator for RangeRev<'a, K, V> {
+    type Item = (&'a K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (key, value) = self.iter.next()?;
+        let in_range = match &self.start {
+            Bound::Included(start) => key >= start,
+            Bound::Excluded(start) => key > start,
+            Bound::Unbounded => true,
+        };
+
+        if in_range {
+            Some((key, value))
+        } else {
+            self.iter.stack.clear();
+            None
+        }
+    }
+}
+
+/*whole nodes at a time, in key order: every leaf comes out as one slice of keys
+ * and values, the separators between them live in internal nodes and come out
+ * as one entry slices
+ * frames are a node and a step: even steps descend into child step / 2,
+ * odd steps yield the key at step / 2
+ */
+pub struct Chunks<'a, K, V> {
+    pub(crate) stack: Vec<(&'a BTreeNode<K, V>, usize)>,
+}
+
+impl<'a, K, V> Iterator for Chunks<'a, K, V> {
+    type Item = (&'a [K], &'a [V]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node, step)) = self.stack.pop() {
+            if node.is_leaf {
+                if node.keys.is_empty() {
+                    continue;
+                }
+                return Some((&node.keys, &node.values));
+            }
+
+            if step > 2 * node.keys.len() {
+                continue;
+            }
+            self.stack.push((node, step + 1));
+
+            let index = step / 2;
+            if step % 2 == 0 {
+                self.stack.push((&node.children[index], 0));
+            } else {
+                return Some((&node.keys[index..=index], &node.values[index..=index]));
+            }
+        }
+        None
+    }
+}
+
+/*k-way merge of iterators that yield entries in strictly increasing key order
+ * a key present in several sources is yielded once, with the value from the newest
+ * source (the one given last), the others are skipped
+ * every step compares the head of each source, which is cheap for the few layers
+ * a layered store usually has
+ */
+pub struct MergeIter<K, V, I> {
+    sources: Vec<I>,
+    heads: Vec<Option<(K, V)>>,
+}
+
+impl<K: Ord, V, I: Iterator<Item = (K, V)>> MergeIter<K, V, I> {
+    /*sources oldest first*/
+    pub fn new<S: IntoIterator<Item = I>>(sources: S) -> Self {
+        let mut sources: Vec<I> = sources.into_iter().collect();
+        let heads = sources.iter_mut().map(Iterator::next).collect();
+        MergeIter { sources, heads }
+    }
+}
+
+impl<K: Ord, V, I: Iterator<Item = (K, V)>> Iterator for MergeIter<K, V, I> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut newest: Option<usize> = None;
+        for (index, head) in self.heads.iter().enumerate() {
+            let Some((key, _)) = head else {
+                continue;
+            };
+            let smallest = match newest {
+                Some(best) => self.heads[best]
+                    .as_ref()
+                    .is_some_and(|(best, _)| key <= best),
+                None => true,
+            };
+            if smallest {
+                newest = Some(index);
+            }
+        }
+
+        let newest = newest?;
+        let entry = std::mem::replace(&mut self.heads[newest], self.sources[newest].next());
+        let (key, value) = entry?;
+        for (head, source) in self.heads.iter_mut().zip(&mut self.sources) {
+            if head.as_ref().is_some_and(|(other, _)| *other == key) {
+                *head = source.next();
+            }
+        }
+        Some((key, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BTreeMap;
+
+    fn evens() -> BTreeMap<u32, u32> {
+        let mut map = BTreeMap::new(2);
+        for key in (0..200).step_by(2) {
+            map.insert(key, key * 10);
+        }
+        map
+    }
+
+    #[test]
+    fn seek_forward_inside_a_scan() {
+        let map = evens();
+        let mut iter = map.iter();
+        assert_eq!(iter.next(), Some((&0, &0)));
+        assert_eq!(iter.next(), Some((&2, &20)));
+
+        /*between keys lands on the next one, the scan carries on from there*/
+        iter.seek(&101);
+        let keys: Vec<u32> = iter.by_ref().take(3).map(|(key, _)| *key).collect();
+        assert_eq!(keys, [102, 104, 106]);
+
+        iter.seek(&150);
+        assert_eq!(
+            iter.map(|(key, _)| *key).collect::<Vec<_>>(),
+            (150..200).step_by(2).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn seek_back_after_the_end() {
+        let map = evens();
+        let mut iter = map.iter();
+        assert_eq!(iter.by_ref().count(), 100);
+        iter.seek(&10);
+        assert_eq!(iter.next(), Some((&10, &100)));
+        iter.seek(&1000);
+        assert_eq!(iter.next(), None);
+
+        let empty: BTreeMap<u32, u32> = BTreeMap::new(2);
+        let mut iter = empty.iter();
+        iter.seek(&0);
+        assert_eq!(iter.next(), None);
+    }
+
+    #[test]
+    fn range_seek_stays_inside_the_range() {
+        let map = evens();
+        let mut range = map.range(20..60);
+        assert_eq!(range.next(), Some((&20, &200)));
+        range.seek(&41);
+        assert_eq!(range.next(), Some((&42, &420)));
+
+        /*before the start goes back to the start, not below it*/
+        range.seek(&0);
+        assert_eq!(range.next(), Some((&20, &200)));
+        let mut excluded = map.range((std::ops::Bound::Excluded(20), std::ops::Bound::Unbounded));
+        excluded.seek(&20);
+        assert_eq!(excluded.next(), Some((&22, &220)));
+
+        range.seek(&58);
+        assert_eq!(range.next(), Some((&58, &580)));
+        assert_eq!(range.next(), None);
+        range.seek(&100);
+        assert_eq!(range.next(), None);
+    }
+}
@@ -0,0 +1,1508 @@
+/*A BTreeMap implementation
+ * the data structure is commonly used in database building
+ * to optomize query searching nistead of a normal HashMap as used in std::collections::HashMap;
+ * BTreeMap store the key Values in sorted order
+ *For more reference check out std:collections::BTreeMap;
+ * September 25, 2024
+ */
+
+mod adaptive;
+mod archive;
+mod clock;
+mod codec;
+mod counted;
+mod cursor;
+mod diff;
+mod disk;
+mod dyn_bucket;
+mod editor;
+mod entry;
+mod fixed;
+#[cfg(feature = "fixtures")]
+pub mod fixtures;
+mod frozen;
+mod handle;
+mod history;
+mod hot;
+mod iter;
+mod log_map;
+mod maintenance;
+mod node;
+mod oplog;
+mod page_store;
+mod range_lock;
+mod soft_delete;
+#[cfg(feature = "telemetry")]
+mod telemetry;
+mod versioned;
+mod view;
+mod visit;
+mod weak;
+
+pub use adaptive::{InsertMode, InsertStats};
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use codec::{Codec, KeyCodec};
+pub use counted::{count_comparisons, Counted};
+pub use cursor::ResumeToken;
+pub use diff::{Change, ChangeKind, SnapshotDiff};
+pub use disk::{DiskBTreeMap, EntryTooLarge, IoStats, StorageError, PAGE_SIZE};
+pub use dyn_bucket::DynBucket;
+pub use editor::RangeEditor;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use fixed::{FixedBTreeMap, Full, OverflowPolicy};
+pub use frozen::{freeze_to_rust_source, FrozenMap};
+pub use handle::{HandleMap, KeyHandle, StaleHandle};
+pub use history::HistoryMap;
+pub use hot::{HotRange, HotRangeTracker};
+pub use iter::{Chunks, IntoIter, Iter, IterRev, MergeIter, Range, RangeRev};
+pub use log_map::{LogMap, SeqOverflow};
+pub use maintenance::{Every, Maintenance, MaintenanceHandle, Policy};
+pub use oplog::Recorder;
+pub use page_store::{FilePages, MemoryPages, PageStore};
+pub use range_lock::{RangeGuard, RangeLocks};
+pub use soft_delete::SoftDeleteMap;
+#[cfg(feature = "telemetry")]
+pub use telemetry::{LatencyHistogram, Metrics};
+pub use versioned::{VersionOverflow, VersionedBTreeMap};
+pub use view::{MapView, RangeStats};
+pub use visit::{NodeInfo, TreeVisitor};
+pub use weak::WeakValueMap;
+
+use adaptive::InsertPattern;
+use node::BTreeNode;
+pub use node::{InvariantError, SplitStrategy};
+use std::borrow::Borrow;
+use std::ops::{Bound, RangeBounds};
+
+/*node size the automatic degree aims for, a handful of cache lines since nodes are searched linearly*/
+const TARGET_NODE_BYTES: usize = 512;
+
+#[derive(Debug)]
+pub struct BTreeMap<K, V> {
+    root: Option<BTreeNode<K, V>>,
+    len: usize,
+    min_degree: usize,
+    append_optimized: bool,
+    split_strategy: SplitStrategy,
+    /*set while insert picks the split strategy itself, see set_adaptive_inserts*/
+    adaptive: Option<InsertPattern>,
+    merge_operator: Option<fn(&mut V, V)>,
+    #[cfg(feature = "telemetry")]
+    metrics: telemetry::MetricsCell,
+}
+
+impl<K: Ord, V> BTreeMap<K, V> {
+    /*nothing is allocated until the first insert, so maps can live in statics
+     * every use is a fresh map, so the telemetry counters inside it are never shared
+     */
+    #[cfg_attr(feature = "telemetry", allow(clippy::declare_interior_mutable_const))]
+    pub const EMPTY: Self = Self::new_auto();
+
+    pub const fn new(min_degree: usize) -> Self {
+        BTreeMap {
+            root: None,
+            len: 0,
+            min_degree,
+            append_optimized: false,
+            split_strategy: SplitStrategy::Middle,
+            adaptive: None,
+            merge_operator: None,
+            #[cfg(feature = "telemetry")]
+            metrics: telemetry::MetricsCell::new(),
+        }
+    }
+
+    /*entries have to be in strictly increasing key order, nothing is compared*/
+    pub fn from_sorted_vec(min_degree: usize, entries: Vec<(K, V)>) -> Self {
+        debug_assert!(
+            entries.windows(2).all(|pair| pair[0].0 < pair[1].0),
+            "from_sorted_vec needs entries sorted by key without duplicates"
+        );
+
+        let mut map = Self::new(min_degree);
+        map.len = entries.len();
+        if !entries.is_empty() {
+            map.root = Some(BTreeNode::from_sorted(min_degree, entries));
+        }
+        map.validate();
+        map
+    }
+
+    /*bulk load from entries in strictly increasing key order, see from_sorted_vec*/
+    pub fn from_sorted_iter<I: IntoIterator<Item = (K, V)>>(min_degree: usize, entries: I) -> Self {
+        Self::from_sorted_vec(min_degree, entries.into_iter().collect())
+    }
+
+    /*bulk load from unsorted entries that may repeat keys, like from_iter, but the values of
+     * a repeated key are folded with `resolve(kept, later)` in input order instead of the
+     * last one winning, so no HashMap pass is needed to combine them first
+     */
+    pub fn from_iter_dedup<I, F>(iter: I, resolve: F) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        F: FnMut(&mut V, V),
+    {
+        Self::from_sorted_vec(
+            Self::auto_min_degree(TARGET_NODE_BYTES),
+            sort_entries_with(iter.into_iter().collect(), resolve),
+        )
+    }
+
+    /*pick min_degree from the entry size instead of guessing, see auto_min_degree*/
+    pub const fn new_auto() -> Self {
+        Self::new(Self::auto_min_degree(TARGET_NODE_BYTES))
+    }
+
+    /*heuristic: fit as many keys and values as possible into `target_node_bytes`
+     * a full node holds 2 * min_degree - 1 entries, so min_degree is about half of that,
+     * never below 2 (the smallest valid B-tree) and capped at 64 because lookups scan
+     * nodes linearly, zero sized keys and values count as one byte
+     * call new(min_degree) directly to override it
+     */
+    pub const fn auto_min_degree(target_node_bytes: usize) -> usize {
+        let entry_bytes = std::mem::size_of::<K>() + std::mem::size_of::<V>();
+        let max_keys = target_node_bytes / if entry_bytes == 0 { 1 } else { entry_bytes };
+        let min_degree = max_keys.div_ceil(2);
+        if min_degree < 2 {
+            2
+        } else if min_degree > 64 {
+            64
+        } else {
+            min_degree
+        }
+    }
+
+    /*tuned for monotonically increasing keys (logs, metrics):
+     * keys past the current maximum go straight down the right edge and
+     * right edge splits keep the left half full, so the nodes on the right
+     * spine may hold fewer keys than usual until they fill up
+     */
+    pub const fn new_time_series(min_degree: usize) -> Self {
+        BTreeMap {
+            root: None,
+            len: 0,
+            min_degree,
+            append_optimized: true,
+            split_strategy: SplitStrategy::Middle,
+            adaptive: None,
+            merge_operator: None,
+            #[cfg(feature = "telemetry")]
+            metrics: telemetry::MetricsCell::new(),
+        }
+    }
+
+    /*insert a key, or replace the value of a key that is already present
+     * and hand back the old value, the key itself is kept as it was
+     */
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        #[cfg(feature = "telemetry")]
+        let started = std::time::Instant::now();
+
+        let fast_path = self.adapt_to(&key)
+            || self.append_optimized && self.last_key().is_none_or(|last| key > *last);
+        let replaced = if fast_path {
+            self.push_back(key, value);
+            None
+        } else {
+            let mut root = self.take_root();
+            if root.keys.len() == 2 * self.min_degree - 1 {
+                /*the old root moves into the new one, nothing is copied*/
+                let split = root.split_point(self.split_strategy, true, &key);
+                let mut new_root = BTreeNode::new(self.min_degree, false);
+                new_root.children.push(root);
+                new_root.split_child_at(0, split);
+                root = new_root;
+            }
+
+            let replaced = root.insert_non_full(key, value, self.split_strategy, true);
+            self.root = Some(root);
+            if replaced.is_none() {
+                self.len += 1;
+            }
+            replaced
+        };
+
+        #[cfg(feature = "telemetry")]
+        self.metrics.record_insert(started.elapsed());
+        self.validate();
+        replaced
+    }
+
+    /*the root, or an empty leaf for an empty map, the caller puts it back*/
+    fn take_root(&mut self) -> BTreeNode<K, V> {
+        self.root
+            .take()
+            .unwrap_or_else(|| BTreeNode::new(self.min_degree, true))
+    }
+
+    /*where insert splits full nodes, see SplitStrategy
+     * leaving a biased strategy packs the spine it relaxed back to the usual node sizes,
+     * the entry API always splits in the middle, a strategy set here turns adaptive
+     * inserts off
+     */
+    pub fn set_split_strategy(&mut self, strategy: SplitStrategy) {
+        self.adaptive = None;
+        self.apply_split_strategy(strategy);
+    }
+
+    fn apply_split_strategy(&mut self, strategy: SplitStrategy) {
+        let (left, right) = (self.relaxed_left_spine(), self.relaxed_right_spine());
+        self.split_strategy = strategy;
+        if left && !self.relaxed_left_spine() {
+            self.repair_left_border();
+        }
+        if right && !self.relaxed_right_spine() {
+            self.repair_right_border();
+        }
+        self.validate();
+    }
+
+    pub fn split_strategy(&self) -> SplitStrategy {
+        self.split_strategy
+    }
+
+    /*spines whose nodes may hold fewer than min_degree - 1 keys*/
+    fn relaxed_left_spine(&self) -> bool {
+        self.split_strategy == SplitStrategy::LeftBiased
+    }
+
+    fn relaxed_right_spine(&self) -> bool {
+        self.append_optimized || self.split_strategy == SplitStrategy::RightBiased
+    }
+
+    /*the operator folds an operand into the stored value, e.g. adding a delta to a counter
+     * it has to be associative since operands are applied in the order they arrive
+     */
+    pub fn set_merge_operator(&mut self, merge_operator: fn(&mut V, V)) {
+        self.merge_operator = Some(merge_operator);
+    }
+
+    /*write an operand without reading the value first
+     * in memory there is nothing to defer to, so the operand collapses into
+     * the stored value right away, a missing key just stores the operand
+     * panics if no operator was registered with set_merge_operator
+     */
+    pub fn merge(&mut self, key: K, operand: V) {
+        let merge_operator = self
+            .merge_operator
+            .expect("merge called without a registered merge operator");
+
+        match self.get_mut(&key) {
+            Some(value) => merge_operator(value, operand),
+            None => {
+                self.insert(key, operand);
+            }
+        }
+    }
+
+    /*append a key larger than every key in the map, without comparing against anything
+     * but the right spine, which is only packed if this map keeps it relaxed
+     */
+    pub fn push_back(&mut self, key: K, value: V) {
+        debug_assert!(
+            self.last_key().is_none_or(|last| key > *last),
+            "push_back needs a key larger than every key in the map"
+        );
+
+        let packed = self.relaxed_right_spine();
+        let mut root = self.take_root();
+        if root.keys.len() == 2 * self.min_degree - 1 {
+            let split = if packed {
+                root.packed_split()
+            } else {
+                self.min_degree - 1
+            };
+            let mut new_root = BTreeNode::new(self.min_degree, false);
+            new_root.children.push(root);
+            new_root.split_child_at(0, split);
+            root = new_root;
+        }
+
+        root.push_back(key, value, packed);
+        self.root = Some(root);
+        self.len += 1;
+    }
+
+    /*the deepest key on the left spine is the smallest one, found without an iterator*/
+    pub fn first_key(&self) -> Option<&K> {
+        let mut node = self.root.as_ref()?;
+        let mut first = node.keys.first();
+        while !node.is_leaf {
+            node = node.children.first().unwrap();
+            if let Some(key) = node.keys.first() {
+                first = Some(key);
+            }
+        }
+        first
+    }
+
+    /*the deepest key on the right spine is the largest one*/
+    pub fn last_key(&self) -> Option<&K> {
+        let mut node = self.root.as_ref()?;
+        let mut last = node.keys.last();
+        while !node.is_leaf {
+            node = node.children.last().unwrap();
+            if let Some(key) = node.keys.last() {
+                last = Some(key);
+            }
+        }
+        last
+    }
+
+    /*remove a key and return its value
+     * underfull nodes on the way back up borrow from or merge with a sibling,
+     * a root left without keys gives way to its only child
+     */
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        #[cfg(feature = "telemetry")]
+        let started = std::time::Instant::now();
+
+        let removed = self.root.as_mut().and_then(|root| root.remove(key));
+        if removed.is_some() {
+            self.len -= 1;
+        }
+        self.fix_top();
+
+        #[cfg(feature = "telemetry")]
+        self.metrics.record_remove(started.elapsed());
+        self.validate();
+        removed
+    }
+
+    /*exchange the values stored under two existing keys without cloning either,
+     * returns false (and changes nothing) unless both keys are present
+     */
+    pub fn swap(&mut self, k1: &K, k2: &K) -> bool {
+        let (low, high) = match k1.cmp(k2) {
+            std::cmp::Ordering::Less => (k1, k2),
+            std::cmp::Ordering::Greater => (k2, k1),
+            std::cmp::Ordering::Equal => return self.get(k1).is_some(),
+        };
+        match self.root {
+            Some(ref mut root) => root.swap_values(low, high),
+            None => false,
+        }
+    }
+
+    /*remove a batch of keys in one shared traversal instead of a search per key, the
+     * nodes left short are repaired once at the end, see BTreeNode::remove_sorted
+     * the removed values come back in the order of `keys`, a repeated key gets its value
+     * the first time and None after that, like removing the keys one by one
+     */
+    pub fn remove_many(&mut self, keys: &[K]) -> Vec<Option<V>> {
+        let mut sorted: Vec<&K> = keys.iter().collect();
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        let mut removed = Vec::new();
+        if let Some(ref mut root) = self.root {
+            root.remove_sorted(&sorted, &mut removed);
+        }
+        self.len -= removed.len();
+        self.fix_top();
+        self.validate();
+
+        removed.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        let mut removed: Vec<(K, Option<V>)> = removed
+            .into_iter()
+            .map(|(key, value)| (key, Some(value)))
+            .collect();
+        keys.iter()
+            .map(|key| match removed.binary_search_by(|(k, _)| k.cmp(key)) {
+                Ok(index) => removed[index].1.take(),
+                Err(_) => None,
+            })
+            .collect()
+    }
+
+    /*apply a batch of updates in one shared traversal instead of a search per key,
+     * keys that are missing are skipped, repeated keys are updated once
+     */
+    pub fn update_many<F: FnMut(&K, &mut V)>(&mut self, keys: &[K], mut f: F) {
+        let mut sorted: Vec<&K> = keys.iter().collect();
+        sorted.sort_unstable();
+        sorted.dedup();
+
+        if let Some(ref mut root) = self.root {
+            root.update_sorted(&sorted, &mut f);
+        }
+    }
+
+    /*boundary entries of a key window in O(height), e.g. the latest event before T
+     * without building an iterator
+     */
+    pub fn first_in_range<R: RangeBounds<K>>(&self, range: R) -> Option<(&K, &V)> {
+        let (key, value) = self.root.as_ref()?.first_from(range.start_bound())?;
+        range.contains(key).then_some((key, value))
+    }
+
+    pub fn last_in_range<R: RangeBounds<K>>(&self, range: R) -> Option<(&K, &V)> {
+        let (key, value) = self.root.as_ref()?.last_until(range.end_bound())?;
+        range.contains(key).then_some((key, value))
+    }
+
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        let mut iter = Iter {
+            root: self.root.as_ref(),
+            stack: Vec::new(),
+        };
+        if let Some(ref root) = self.root {
+            iter.push_left_edge(root);
+        }
+        iter
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.iter().map(|(_, value)| value)
+    }
+
+    /*largest key first, walking down from the right spine*/
+    pub fn iter_rev(&self) -> IterRev<'_, K, V> {
+        let mut iter = IterRev { stack: Vec::new() };
+        if let Some(ref root) = self.root {
+            iter.push_right_edge(root);
+        }
+        iter
+    }
+
+    pub fn keys_rev(&self) -> impl Iterator<Item = &K> {
+        self.iter_rev().map(|(key, _)| key)
+    }
+
+    pub fn chunks(&self) -> Chunks<'_, K, V> {
+        Chunks {
+            stack: self.root.iter().map(|root| (root, 0)).collect(),
+        }
+    }
+
+    /*consecutive runs of keys that map to the same group, e.g. the same prefix or time bucket
+     * keys are sorted, so `group_of` only has to be monotonic for every group to come out once
+     */
+    pub fn group_by<G: PartialEq, F: FnMut(&K) -> G>(
+        &self,
+        mut group_of: F,
+    ) -> impl Iterator<Item = (G, Vec<(&K, &V)>)> {
+        let mut entries = self
+            .chunks()
+            .flat_map(|(keys, values)| keys.iter().zip(values));
+        let mut pending = None;
+
+        std::iter::from_fn(move || {
+            let (group, first) = pending
+                .take()
+                .or_else(|| entries.next().map(|entry| (group_of(entry.0), entry)))?;
+
+            let mut members = vec![first];
+            for entry in entries.by_ref() {
+                let next_group = group_of(entry.0);
+                if next_group != group {
+                    pending = Some((next_group, entry));
+                    break;
+                }
+                members.push(entry);
+            }
+            Some((group, members))
+        })
+    }
+
+    /*approximate q-quantile of the keys (0.0 is the smallest, 1.0 the largest) in O(height)
+     * nodes keep no subtree counts, so every child is treated as holding the same
+     * number of entries, B-tree balance keeps siblings within about a factor of two
+     * 0.0 and 1.0 are exact, in between, on a tree fresh from from_sorted_vec (siblings
+     * differ by at most one child or key) the rank of the returned key is within
+     * len / (2 * min_degree) + 2 * min_degree of q * (len - 1), inserts and removes since
+     * then unbalance siblings and loosen that
+     */
+    pub fn quantile(&self, q: f64) -> Option<&K> {
+        let mut q = q.clamp(0.0, 1.0);
+        let mut node = self.root.as_ref()?;
+        while !node.is_leaf {
+            let scaled = q * node.children.len() as f64;
+            let index = (scaled as usize).min(node.children.len() - 1);
+            q = scaled - index as f64;
+            node = &node.children[index];
+        }
+
+        let index = (q * node.keys.len() as f64) as usize;
+        node.keys.get(index.min(node.keys.len().saturating_sub(1)))
+    }
+
+    /*(entries, bytes) a range would yield, from two descents instead of a scan
+     * same equal children assumption as quantile, bytes only count the inline size of
+     * keys and values, not what they own on the heap
+     */
+    pub fn estimate_range_size<R: RangeBounds<K>>(&self, range: R) -> (usize, usize) {
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.fraction_before(|k| k < start),
+            Bound::Excluded(start) => self.fraction_before(|k| k <= start),
+            Bound::Unbounded => 0.0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.fraction_before(|k| k <= end),
+            Bound::Excluded(end) => self.fraction_before(|k| k < end),
+            Bound::Unbounded => 1.0,
+        };
+
+        let entries = ((end - start).max(0.0) * self.len as f64).round() as usize;
+        let entry_bytes = std::mem::size_of::<K>() + std::mem::size_of::<V>();
+        (entries, entries * entry_bytes)
+    }
+
+    /*estimated share of the entries whose keys satisfy `before`, which has to hold for
+     * a prefix of the keys
+     */
+    fn fraction_before<F: Fn(&K) -> bool>(&self, before: F) -> f64 {
+        let Some(mut node) = self.root.as_ref() else {
+            return 0.0;
+        };
+        let (mut low, mut width) = (0.0, 1.0);
+        loop {
+            let pos = node.keys.partition_point(&before);
+            if node.is_leaf {
+                if node.keys.is_empty() {
+                    return low;
+                }
+                return low + width * pos as f64 / node.keys.len() as f64;
+            }
+
+            width /= node.children.len() as f64;
+            low += width * pos as f64;
+            node = &node.children[pos];
+        }
+    }
+
+    /*retention: drop every entry with a key smaller than `key`*/
+    pub fn trim_before(&mut self, key: &K) {
+        if let Some(ref mut root) = self.root {
+            self.len -= root.trim_before(key);
+        }
+
+        self.repair_left_border();
+        self.validate();
+    }
+
+    /*every entry from `key` on moves into the returned map, which keeps this map's settings
+     * the tree is cut along the path to `key` and only the nodes along the two new edges
+     * are repaired, counting the moved entries visits the nodes (not the entries) of the
+     * returned map
+     */
+    pub fn split_off<Q>(&mut self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let right = match self.root {
+            Some(ref mut root) => root.split_off(key),
+            None => return self.split_with(None, 0),
+        };
+        let len = right.len();
+        self.split_with(Some(right), len)
+    }
+
+    /*the first `rank` entries stay, the rest move into the returned map
+     * same cut as split_off, finding it counts the entries of the subtrees left of it
+     */
+    pub fn split_off_at(&mut self, rank: usize) -> Self {
+        let rank = rank.min(self.len);
+        let right = match self.root {
+            Some(ref mut root) => root.split_off_at(rank),
+            None => return self.split_with(None, 0),
+        };
+        let len = self.len - rank;
+        self.split_with(Some(right), len)
+    }
+
+    /*the map for the `len` entries under `right`, cut off this one, with both new edges repaired*/
+    fn split_with(&mut self, right: Option<BTreeNode<K, V>>, len: usize) -> Self {
+        let mut upper = BTreeMap {
+            root: right,
+            len,
+            append_optimized: self.append_optimized,
+            split_strategy: self.split_strategy,
+            adaptive: self.adaptive.clone(),
+            merge_operator: self.merge_operator,
+            ..Self::new(self.min_degree)
+        };
+        self.len -= len;
+
+        self.repair_right_border();
+        upper.repair_left_border();
+        self.validate();
+        upper.validate();
+        upper
+    }
+
+    /*move every entry of `other` into this map and leave `other` empty, values from `other`
+     * win for keys in both
+     * when all keys of one map sort below all keys of the other (and both use the same degree)
+     * the trees are joined along one edge in O(height), otherwise both are merged into
+     * a freshly packed tree in linear time
+     */
+    pub fn append(&mut self, other: &mut Self) {
+        if other.is_empty() {
+            return;
+        }
+
+        let below = match (self.last_key(), other.first_key()) {
+            (Some(last), Some(first)) => last < first,
+            _ => true,
+        };
+        let above = match (self.first_key(), other.last_key()) {
+            (Some(first), Some(last)) => last < first,
+            _ => true,
+        };
+        if self.min_degree != other.min_degree || !(below || above) {
+            self.merge_from(other);
+            return;
+        }
+
+        /*a relaxed spine that ends up inside the joined tree, or on an edge this map keeps
+         * strict, has to be strict first
+         */
+        if self.relaxed_right_spine() && below {
+            self.repair_right_border();
+        }
+        if other.relaxed_right_spine() && !(below && self.relaxed_right_spine()) {
+            other.repair_right_border();
+        }
+        if self.relaxed_left_spine() && !below {
+            self.repair_left_border();
+        }
+        if other.relaxed_left_spine() && (below || !self.relaxed_left_spine()) {
+            other.repair_left_border();
+        }
+
+        let theirs = other.root.take().unwrap();
+        self.root = Some(match self.root.take() {
+            None => theirs,
+            Some(mine) if below => BTreeNode::join(mine, theirs),
+            Some(mine) => BTreeNode::join(theirs, mine),
+        });
+        self.len += std::mem::take(&mut other.len);
+        self.validate();
+    }
+
+    /*append for overlapping keys or different degrees: one sorted merge, then bottom up packing*/
+    fn merge_from(&mut self, other: &mut Self) {
+        let mine = BTreeMap {
+            root: self.root.take(),
+            ..Self::new(self.min_degree)
+        };
+        let theirs = BTreeMap {
+            root: other.root.take(),
+            ..Self::new(other.min_degree)
+        };
+        other.len = 0;
+
+        let entries: Vec<(K, V)> = MergeIter::new([mine.into_iter(), theirs.into_iter()]).collect();
+        self.len = entries.len();
+        self.root = Some(BTreeNode::from_sorted(self.min_degree, entries));
+        self.validate();
+    }
+
+    /*fix_left_border and fix_right_border, plus collapsing roots left without keys*/
+    fn repair_left_border(&mut self) {
+        self.fix_top();
+        if let Some(ref mut root) = self.root {
+            root.fix_left_border();
+        }
+        self.fix_top();
+    }
+
+    fn repair_right_border(&mut self) {
+        self.fix_top();
+        if let Some(ref mut root) = self.root {
+            root.fix_right_border();
+        }
+        self.fix_top();
+    }
+
+    /*a root left without keys is replaced by its only child (or nothing at all)*/
+    fn fix_top(&mut self) {
+        while let Some(root) = self.root.take() {
+            if !root.keys.is_empty() {
+                self.root = Some(root);
+                break;
+            }
+            self.root = root.children.into_iter().next();
+        }
+    }
+
+    /*structural checks: key order within and across nodes, node sizes, child counts and
+     * all leaves at the same depth, the first problem found is described in the error
+     */
+    pub fn check_invariants(&self) -> Result<(), InvariantError> {
+        match self.root {
+            Some(ref root) => root
+                .check_invariants(
+                    0,
+                    self.relaxed_left_spine(),
+                    self.relaxed_right_spine(),
+                    None,
+                    None,
+                )
+                .map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /*with the debug-validate feature every structural change is checked right away*/
+    fn validate(&self) {
+        #[cfg(feature = "debug-validate")]
+        if let Err(problem) = self.check_invariants() {
+            panic!("tree invariant broken: {}", problem);
+        }
+    }
+
+    /*latency of every operation so far, range only covers finding the start of the range*/
+    #[cfg(feature = "telemetry")]
+    pub fn metrics(&self) -> Metrics {
+        self.metrics.snapshot()
+    }
+
+    /*histogram[depth][n] is the number of nodes at that depth holding n keys
+     * lots of nodes near min_degree - 1 keys after heavy churn means the tree is fragmented
+     */
+    pub fn occupancy_histogram(&self) -> Vec<Vec<usize>> {
+        let mut histogram = Vec::new();
+        if let Some(ref root) = self.root {
+            root.collect_occupancy(0, &mut histogram);
+        }
+        histogram
+    }
+
+    /*any borrowed form of the key works for lookups, e.g. &str for Arc<str> or String keys*/
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        #[cfg(feature = "telemetry")]
+        let started = std::time::Instant::now();
+
+        let found = self
+            .root
+            .as_ref()
+            .and_then(|node| self.search_in_node(node, key));
+
+        #[cfg(feature = "telemetry")]
+        self.metrics.record_search(started.elapsed());
+        found
+    }
+
+    /*use recursion to search the node tree*/
+    fn search_in_node<'a, Q>(&self, node: &'a BTreeNode<K, V>, key: &Q) -> Option<&'a V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = node
+            .keys
+            .iter()
+            .position(|k| k.borrow() >= key)
+            .unwrap_or(node.keys.len());
+
+        if pos < node.keys.len() && node.keys[pos].borrow() == key {
+            return Some(&node.values[pos]);
+        }
+
+        /*if we get to this point  and the node is leaf, then key does not exist in the tree*/
+        if node.is_leaf {
+            None
+        } else {
+            node.children
+                .get(pos)
+                .and_then(|child| self.search_in_node(child, key))
+        }
+    }
+
+    pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.root
+            .as_mut()
+            .and_then(|node| Self::search_in_node_mut(node, key))
+    }
+
+    fn search_in_node_mut<'a, Q>(node: &'a mut BTreeNode<K, V>, key: &Q) -> Option<&'a mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = node
+            .keys
+            .iter()
+            .position(|k| k.borrow() >= key)
+            .unwrap_or(node.keys.len());
+
+        if pos < node.keys.len() && node.keys[pos].borrow() == key {
+            return Some(&mut node.values[pos]);
+        }
+
+        if node.is_leaf {
+            None
+        } else {
+            node.children
+                .get_mut(pos)
+                .and_then(|child| Self::search_in_node_mut(child, key))
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        self.iter().next()
+    }
+
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        self.iter_rev().next()
+    }
+
+    /*number of entries, kept up to date by every mutation instead of walking the tree*/
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn min_degree(&self) -> usize {
+        self.min_degree
+    }
+
+    /*drop every entry, the degree, the time series mode, the split strategy and the merge
+     * operator stay
+     */
+    pub fn clear(&mut self) {
+        self.root = None;
+        self.len = 0;
+    }
+}
+
+/*the range iterators and editors keep their own copy of the bounds of their range,
+ * partition_points hands out copies of keys
+ */
+impl<K: Ord + Clone, V> BTreeMap<K, V> {
+    /*hand out access to the keys inside `range` only, see MapView*/
+    pub fn view<R: RangeBounds<K>>(&self, range: R) -> MapView<'_, K, V> {
+        MapView::new(
+            self,
+            range.start_bound().cloned(),
+            range.end_bound().cloned(),
+        )
+    }
+
+    /*exact counterpart of estimate_range_size for planners that can afford a scan of the
+     * range, nodes keep no aggregates of their values, so there are none to report
+     */
+    pub fn range_stats<R: RangeBounds<K>>(&self, range: R) -> RangeStats<'_, K> {
+        let mut stats = RangeStats {
+            entries: 0,
+            bytes: 0,
+            min_key: None,
+            max_key: None,
+        };
+        for (key, _) in self.range(range) {
+            stats.min_key.get_or_insert(key);
+            stats.max_key = Some(key);
+            stats.entries += 1;
+        }
+        stats.bytes = stats.entries * (std::mem::size_of::<K>() + std::mem::size_of::<V>());
+        stats
+    }
+
+    /*stage changes to the keys inside `range` and commit or drop them together, see
+     * RangeEditor
+     */
+    pub fn edit_range<R: RangeBounds<K>>(&mut self, range: R) -> RangeEditor<'_, K, V> {
+        RangeEditor::new(
+            self,
+            range.start_bound().cloned(),
+            range.end_bound().cloned(),
+        )
+    }
+
+    /*entries inside `range`, smallest key first
+     * the start point is found by descending along the lower bound, not by skipping entries
+     */
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V> {
+        #[cfg(feature = "telemetry")]
+        let started = std::time::Instant::now();
+
+        let mut iter = Iter {
+            root: self.root.as_ref(),
+            stack: Vec::new(),
+        };
+        iter.descend_to(range.start_bound());
+
+        #[cfg(feature = "telemetry")]
+        self.metrics.record_range(started.elapsed());
+        Range {
+            iter,
+            start: range.start_bound().cloned(),
+            end: range.end_bound().cloned(),
+        }
+    }
+
+    /*entries inside `range`, largest key first
+     * the start point is found by descending along the upper bound, not by skipping entries
+     */
+    pub fn range_rev<R: RangeBounds<K>>(&self, range: R) -> RangeRev<'_, K, V> {
+        #[cfg(feature = "telemetry")]
+        let started = std::time::Instant::now();
+
+        let mut iter = IterRev { stack: Vec::new() };
+        let mut node = self.root.as_ref();
+        while let Some(current) = node {
+            let pos = current
+                .keys
+                .iter()
+                .position(|k| match range.end_bound() {
+                    Bound::Included(end) => k > end,
+                    Bound::Excluded(end) => k >= end,
+                    Bound::Unbounded => false,
+                })
+                .unwrap_or(current.keys.len());
+            iter.stack.push((current, pos));
+            node = current.children.get(pos);
+        }
+
+        #[cfg(feature = "telemetry")]
+        self.metrics.record_range(started.elapsed());
+        RangeRev {
+            iter,
+            start: range.start_bound().cloned(),
+        }
+    }
+
+    /*up to n - 1 keys that cut the map into n ranges of about the same size, e.g. one per
+     * worker: the i-th range starts at the (i - 1)-th key and ends before the i-th
+     * the keys are quantiles, so it's O(n * height) and the ranges are only as even as
+     * quantile is accurate, small maps can give fewer keys than asked for
+     */
+    pub fn partition_points(&self, n: usize) -> Vec<K> {
+        let Some((first, _)) = self.first_key_value() else {
+            return Vec::new();
+        };
+
+        /*a repeated key or the smallest one would only add an empty range*/
+        let mut points: Vec<K> = Vec::with_capacity(n.saturating_sub(1));
+        for i in 1..n {
+            let key = self.quantile(i as f64 / n as f64).unwrap();
+            if key > first && points.last().is_none_or(|last| last < key) {
+                points.push(key.clone());
+            }
+        }
+        points
+    }
+}
+
+/*copies for callers that keep their own buffers*/
+impl<K: Ord + Clone, V: Clone> BTreeMap<K, V> {
+    /*replace the contents of `out` with the entries inside `range` and return how many,
+     * the entries already in `out` are overwritten with clone_from, so their allocations
+     * (e.g. of Strings) are reused too, and a buffer that was large enough doesn't grow
+     */
+    pub fn read_range_into<R: RangeBounds<K>>(&self, range: R, out: &mut Vec<(K, V)>) -> usize {
+        let mut written = 0;
+        for (key, value) in self.range(range) {
+            match out.get_mut(written) {
+                Some((slot_key, slot_value)) => {
+                    slot_key.clone_from(key);
+                    slot_value.clone_from(value);
+                }
+                None => out.push((key.clone(), value.clone())),
+            }
+            written += 1;
+        }
+        out.truncate(written);
+        written
+    }
+
+    /*fill `out` from the front with the entries inside `range` until either runs out,
+     * returns how many slots were written, continue after the last one for the rest
+     */
+    pub fn read_range_into_slice<R: RangeBounds<K>>(&self, range: R, out: &mut [(K, V)]) -> usize {
+        let mut written = 0;
+        for ((key, value), (slot_key, slot_value)) in self.range(range).zip(out.iter_mut()) {
+            slot_key.clone_from(key);
+            slot_value.clone_from(value);
+            written += 1;
+        }
+        written
+    }
+}
+
+impl<K: Ord + Clone, V: Ord + Clone> BTreeMap<K, V> {
+    /*reverse lookup from value to every key holding it, keys stay in ascending order
+     * one stable sort by value, then the groups are packed bottom up
+     */
+    pub fn invert(&self) -> BTreeMap<V, Vec<K>> {
+        let mut pairs: Vec<(&V, &K)> = self
+            .chunks()
+            .flat_map(|(keys, values)| values.iter().zip(keys))
+            .collect();
+        pairs.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut inverted: Vec<(V, Vec<K>)> = Vec::new();
+        for (value, key) in pairs {
+            match inverted.last_mut() {
+                Some((last, keys)) if last == value => keys.push(key.clone()),
+                _ => inverted.push((value.clone(), vec![key.clone()])),
+            }
+        }
+
+        BTreeMap::from_sorted_vec(
+            BTreeMap::<V, Vec<K>>::auto_min_degree(TARGET_NODE_BYTES),
+            inverted,
+        )
+    }
+}
+
+/*std keeps its entries sorted already, so they can be packed without any comparisons*/
+impl<K: Ord, V> From<std::collections::BTreeMap<K, V>> for BTreeMap<K, V> {
+    fn from(map: std::collections::BTreeMap<K, V>) -> Self {
+        Self::from_sorted_vec(
+            Self::auto_min_degree(TARGET_NODE_BYTES),
+            map.into_iter().collect(),
+        )
+    }
+}
+
+/*same as new_auto*/
+impl<K: Ord, V> Default for BTreeMap<K, V> {
+    fn default() -> Self {
+        Self::new_auto()
+    }
+}
+
+/*sort by key and keep one entry per key: the first key with the last value,
+ * the same outcome as inserting the entries one by one
+ */
+fn sort_entries<K: Ord, V>(entries: Vec<(K, V)>) -> Vec<(K, V)> {
+    sort_entries_with(entries, |kept, later| *kept = later)
+}
+
+/*the sort is stable, so `resolve` sees the values of a key in input order*/
+fn sort_entries_with<K: Ord, V>(
+    mut entries: Vec<(K, V)>,
+    mut resolve: impl FnMut(&mut V, V),
+) -> Vec<(K, V)> {
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut sorted: Vec<(K, V)> = Vec::with_capacity(entries.len());
+    for (key, value) in entries {
+        match sorted.last_mut() {
+            Some(last) if last.0 == key => resolve(&mut last.1, value),
+            _ => sorted.push((key, value)),
+        }
+    }
+    sorted
+}
+
+/*sorted once and packed bottom up, instead of paying a top down insert per entry*/
+impl<K: Ord, V> FromIterator<(K, V)> for BTreeMap<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self::from_sorted_vec(
+            Self::auto_min_degree(TARGET_NODE_BYTES),
+            sort_entries(iter.into_iter().collect()),
+        )
+    }
+}
+
+/*an empty map is bulk loaded like from_iter (keeping its degree, mode and merge operator),
+ * otherwise the entries are inserted in key order so consecutive inserts walk the same path
+ */
+impl<K: Ord, V> Extend<(K, V)> for BTreeMap<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        let entries = sort_entries(iter.into_iter().collect());
+        if self.is_empty() && !entries.is_empty() {
+            self.len = entries.len();
+            self.root = Some(BTreeNode::from_sorted(self.min_degree, entries));
+            self.validate();
+            return;
+        }
+
+        for (key, value) in entries {
+            self.insert(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap as StdMap;
+
+    /*xorshift, enough to pick operations and keys reproducibly*/
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u32) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as u32
+        }
+    }
+
+    fn assert_same(map: &BTreeMap<u32, u32>, model: &StdMap<u32, u32>, step: usize) {
+        if let Err(error) = map.check_invariants() {
+            panic!("step {step}: {error}");
+        }
+        assert_eq!(map.len(), model.len(), "step {step}");
+        assert_eq!(map.first_key(), model.keys().next(), "step {step}");
+        assert_eq!(map.last_key(), model.keys().next_back(), "step {step}");
+        assert!(
+            map.iter().eq(model.iter()),
+            "step {step}: entries differ from std"
+        );
+    }
+
+    /*random operations on `map` and on a std map side by side, the trees are compared
+     * entry by entry and checked after every step
+     */
+    fn run_against_std(mut map: BTreeMap<u32, u32>, seed: u64, steps: usize) {
+        const KEYS: u32 = 400;
+        let mut rng = Rng(seed);
+        let mut model = StdMap::new();
+
+        for step in 0..steps {
+            let key = rng.below(KEYS);
+            let value = rng.below(1000);
+            match rng.below(20) {
+                0..=6 => assert_eq!(map.insert(key, value), model.insert(key, value)),
+                7..=9 => assert_eq!(map.remove(&key), model.remove(&key)),
+                10 => assert_eq!(map.get(&key), model.get(&key)),
+                11 => {
+                    *map.entry(key).or_insert(value) += 1;
+                    *model.entry(key).or_insert(value) += 1;
+                }
+                12 => match (map.entry(key), model.entry(key)) {
+                    (
+                        Entry::Occupied(mine),
+                        std::collections::btree_map::Entry::Occupied(theirs),
+                    ) => {
+                        assert_eq!(mine.remove_entry(), theirs.remove_entry());
+                    }
+                    (Entry::Vacant(mine), std::collections::btree_map::Entry::Vacant(theirs)) => {
+                        mine.insert(value);
+                        theirs.insert(value);
+                    }
+                    _ => panic!("step {step}: entry for {key} disagrees with std"),
+                },
+                13 => {
+                    let keys: Vec<u32> = (0..rng.below(40)).map(|_| rng.below(KEYS)).collect();
+                    let expected: Vec<Option<u32>> = keys.iter().map(|k| model.remove(k)).collect();
+                    assert_eq!(map.remove_many(&keys), expected);
+                }
+                14 => {
+                    /*cut and glue back in the same order, append joins along an edge*/
+                    let mut upper = map.split_off(&key);
+                    let model_upper = model.split_off(&key);
+                    assert_same(&map, &model, step);
+                    assert_same(&upper, &model_upper, step);
+                    map.append(&mut upper);
+                    assert!(upper.is_empty());
+                    model.extend(model_upper);
+                }
+                15 => {
+                    /*glue the lower part onto the upper one, the join from the other side*/
+                    let rank = rng.below(map.len() as u32 + 1) as usize;
+                    let mut upper = map.split_off_at(rank);
+                    upper.append(&mut map);
+                    map = upper;
+                }
+                16 => {
+                    /*overlapping keys, append falls back to a merge and `other` wins*/
+                    let mut other = BTreeMap::new(map.min_degree());
+                    for _ in 0..rng.below(30) {
+                        let (key, value) = (rng.below(KEYS), rng.below(1000));
+                        other.insert(key, value);
+                        model.insert(key, value);
+                    }
+                    map.append(&mut other);
+                }
+                17 => {
+                    if rng.below(10) == 0 {
+                        let bound = model.keys().next().copied().unwrap_or(0) + rng.below(20);
+                        map.trim_before(&bound);
+                        model = model.split_off(&bound);
+                    }
+                }
+                18 => {
+                    let next = model.keys().next_back().map_or(0, |last| last + 1);
+                    map.push_back(next, value);
+                    model.insert(next, value);
+                }
+                _ => {
+                    let end = key + rng.below(50);
+                    assert!(map.range(key..end).eq(model.range(key..end)));
+                    assert!(map.range_rev(key..end).eq(model.range(key..end).rev()));
+                }
+            }
+            assert_same(&map, &model, step);
+        }
+    }
+
+    #[test]
+    fn random_operations_match_std() {
+        for (seed, min_degree) in [(1, 2), (2, 3), (3, 5)] {
+            run_against_std(BTreeMap::new(min_degree), seed, 3000);
+        }
+    }
+
+    #[test]
+    fn random_operations_match_std_in_time_series_mode() {
+        for (seed, min_degree) in [(4, 2), (5, 3), (6, 5)] {
+            run_against_std(BTreeMap::new_time_series(min_degree), seed, 3000);
+        }
+    }
+
+    #[test]
+    fn random_operations_match_std_for_every_split_strategy() {
+        let strategies = [
+            SplitStrategy::Middle,
+            SplitStrategy::RightBiased,
+            SplitStrategy::LeftBiased,
+        ];
+        for (seed, strategy) in (7..).zip(strategies) {
+            for min_degree in [2, 3, 5] {
+                let mut map = BTreeMap::new(min_degree);
+                map.set_split_strategy(strategy);
+                run_against_std(map, seed, 3000);
+            }
+        }
+    }
+
+    #[test]
+    fn random_operations_match_std_with_adaptive_inserts() {
+        for (seed, min_degree) in [(10, 2), (11, 3)] {
+            let mut map = BTreeMap::new(min_degree);
+            map.set_adaptive_inserts(true);
+            run_against_std(map, seed, 3000);
+        }
+    }
+
+    /*a middle split on the spine can move the key off it, the child it goes into must not
+     * get a packed split after that
+     */
+    #[test]
+    fn biased_split_leaves_the_spine_after_a_middle_split() {
+        let keys = [
+            625, 575, 905, 788, 572, 458, 427, 380, 429, 626, 164, 637, 961, 53, 375, 507, 889,
+            292, 472, 926, 820, 29, 474, 196, 630, 381, 546, 742, 715, 857, 817, 877, 545, 807,
+            766,
+        ];
+        for strategy in [SplitStrategy::RightBiased, SplitStrategy::LeftBiased] {
+            let mut map = BTreeMap::new(3);
+            map.set_split_strategy(strategy);
+            for key in keys {
+                map.insert(key, ());
+                map.check_invariants().unwrap();
+            }
+            assert_eq!(map.len(), keys.len());
+        }
+    }
+
+    #[test]
+    fn quantile_of_a_bulk_loaded_tree_is_within_the_bound() {
+        assert_eq!(BTreeMap::<u32, ()>::new(2).quantile(0.5), None);
+        for min_degree in [2, 3, 8] {
+            for len in [1, 2, 5, 100, 1000, 4321] {
+                let map =
+                    BTreeMap::from_sorted_vec(min_degree, (0..len).map(|k| (k * 2, ())).collect());
+                assert_eq!(map.quantile(0.0), Some(&0));
+                assert_eq!(map.quantile(1.0), Some(&((len - 1) * 2)));
+                assert_eq!(map.quantile(-1.0), Some(&0));
+                assert_eq!(map.quantile(2.0), Some(&((len - 1) * 2)));
+
+                let bound = len as f64 / (2 * min_degree) as f64 + (2 * min_degree) as f64;
+                for q in [0.1, 0.25, 0.5, 0.75, 0.9] {
+                    let rank = (map.quantile(q).unwrap() / 2) as f64;
+                    let error = (rank - q * (len - 1) as f64).abs();
+                    assert!(
+                        error <= bound,
+                        "min_degree {min_degree}, len {len}, q {q}: off by {error}"
+                    );
+                }
+            }
+        }
+    }
+
+    /*sizes of the ranges partition_points cuts the map into*/
+    fn part_sizes(map: &BTreeMap<u32, ()>, points: &[u32]) -> Vec<usize> {
+        let mut sizes = Vec::new();
+        let mut start = Bound::Unbounded;
+        for point in points {
+            sizes.push(map.range((start, Bound::Excluded(*point))).count());
+            start = Bound::Included(*point);
+        }
+        sizes.push(map.range((start, Bound::Unbounded)).count());
+        sizes
+    }
+
+    #[test]
+    fn partition_points_cut_about_equal_parts() {
+        let map = BTreeMap::from_sorted_vec(4, (0..10_000).map(|k| (k, ())).collect());
+        for n in [2, 3, 4, 7, 16] {
+            let points = map.partition_points(n);
+            assert_eq!(points.len(), n - 1);
+            let sizes = part_sizes(&map, &points);
+            assert_eq!(sizes.iter().sum::<usize>(), 10_000);
+            /*each cut is a quantile, off by at most the bound of quantile*/
+            for (i, point) in points.iter().enumerate() {
+                let target = (i + 1) as f64 / n as f64 * 9_999.0;
+                assert!((*point as f64 - target).abs() <= 10_000.0 / 8.0 + 8.0);
+            }
+            assert!(
+                sizes.iter().all(|&size| size > 10_000 / n / 2),
+                "{n} parts: {sizes:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn partition_points_of_small_maps() {
+        let empty = BTreeMap::<u32, ()>::new(2);
+        assert!(empty.partition_points(4).is_empty());
+
+        let map = BTreeMap::from_sorted_vec(2, (0..3).map(|k| (k, ())).collect());
+        assert!(map.partition_points(0).is_empty());
+        assert!(map.partition_points(1).is_empty());
+        for n in [3, 4, 10, 100] {
+            let points = map.partition_points(n);
+            assert!(points.len() <= 2);
+            assert!(points.windows(2).all(|pair| pair[0] < pair[1]));
+            let sizes = part_sizes(&map, &points);
+            assert!(sizes.iter().all(|&size| size > 0), "{n} parts: {sizes:?}");
+            assert_eq!(sizes.iter().sum::<usize>(), 3);
+        }
+    }
+
+    #[test]
+    fn swap_exchanges_values_only_when_both_keys_exist() {
+        let fresh = || BTreeMap::from_sorted_vec(2, (0..40).map(|k| (k * 2, k * 2)).collect());
+        /*every pair of keys, wherever in the tree they sit, and missing keys around them*/
+        for a in [0, 2, 14, 40, 41, 78, 79, 100] {
+            for b in [0, 6, 40, 41, 52, 78, 100] {
+                let mut map = fresh();
+                let present = a % 2 == 0 && a < 80 && b % 2 == 0 && b < 80;
+                assert_eq!(map.swap(&a, &b), present, "swap({a}, {b})");
+
+                let mut model: StdMap<u32, u32> = (0..40).map(|k| (k * 2, k * 2)).collect();
+                if present && a != b {
+                    model.insert(a, b);
+                    model.insert(b, a);
+                }
+                assert_same(&map, &model, 0);
+            }
+        }
+
+        let mut empty = BTreeMap::<u32, u32>::new(2);
+        assert!(!empty.swap(&1, &1));
+        assert!(!empty.swap(&1, &2));
+    }
+
+    #[test]
+    fn update_many_touches_present_keys_once() {
+        let mut map = BTreeMap::from_sorted_vec(2, (0..30).map(|k| (k * 2, 0)).collect());
+        let mut model: StdMap<u32, u32> = (0..30).map(|k| (k * 2, 0)).collect();
+        /*unsorted, repeated and missing keys, the ends of the key range included*/
+        let keys = [58, 3, 0, 30, 31, 58, 100, 12, 0, 13, 44];
+
+        let mut seen = Vec::new();
+        map.update_many(&keys, |key, value| {
+            seen.push(*key);
+            *value += key + 1;
+        });
+        for key in [0, 12, 30, 44, 58] {
+            *model.get_mut(&key).unwrap() += key + 1;
+        }
+        assert_eq!(seen, vec![0, 12, 30, 44, 58]);
+        assert_same(&map, &model, 0);
+
+        let mut empty = BTreeMap::<u32, u32>::new(2);
+        empty.update_many(&[1, 2], |_, _| unreachable!());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn first_and_last_in_range_match_std() {
+        let map = BTreeMap::from_sorted_vec(2, (0..30).map(|k| (k * 2, k)).collect());
+        let model: StdMap<u32, u32> = (0..30).map(|k| (k * 2, k)).collect();
+
+        let mut bounds = vec![Bound::Unbounded];
+        for key in [0, 1, 10, 11, 30, 57, 58, 59, 100] {
+            bounds.push(Bound::Included(key));
+            bounds.push(Bound::Excluded(key));
+        }
+        for &start in &bounds {
+            for &end in &bounds {
+                let range = (start, end);
+                /*the ranges std refuses, ours come back empty*/
+                let refused = match (start, end) {
+                    (Bound::Excluded(s), Bound::Excluded(e)) => s >= e,
+                    (
+                        Bound::Included(s) | Bound::Excluded(s),
+                        Bound::Included(e) | Bound::Excluded(e),
+                    ) => s > e,
+                    _ => false,
+                };
+                if refused {
+                    assert_eq!(map.first_in_range(range), None, "{range:?}");
+                    assert_eq!(map.last_in_range(range), None, "{range:?}");
+                    continue;
+                }
+                let mut expected = model.range(range);
+                assert_eq!(map.first_in_range(range), expected.next(), "{range:?}");
+                let mut expected = model.range(range);
+                assert_eq!(map.last_in_range(range), expected.next_back(), "{range:?}");
+            }
+        }
+
+        /*empty windows between keys and past either end*/
+        assert_eq!(map.first_in_range(11..12), None);
+        assert_eq!(map.last_in_range(11..12), None);
+        assert_eq!(map.first_in_range(59..), None);
+        assert_eq!(map.last_in_range(..0), None);
+        assert_eq!(BTreeMap::<u32, u32>::new(2).first_in_range(..), None);
+        assert_eq!(BTreeMap::<u32, u32>::new(2).last_in_range(..), None);
+    }
+
+    #[test]
+    fn merge_folds_operands_into_the_value_right_away() {
+        let mut map = BTreeMap::new(2);
+        map.set_merge_operator(|total: &mut u32, delta| *total += delta);
+        map.merge(1, 5);
+        assert_eq!(map.get(&1), Some(&5));
+        map.merge(1, 2);
+        map.merge(2, 7);
+        assert_eq!(map.get(&1), Some(&7));
+        assert_eq!(map.get(&2), Some(&7));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "without a registered merge operator")]
+    fn merge_without_an_operator_panics() {
+        BTreeMap::new(2).merge(1, 1u32);
+    }
+}
@@ -0,0 +1,150 @@
+use crate::BTreeMap;
+use std::ops::{Range, RangeBounds};
+
+/*insert_at was given u64::MAX, which is kept free so the next append always has a number*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeqOverflow;
+
+impl std::fmt::Display for SeqOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "sequence number u64::MAX leaves no number for the next append"
+        )
+    }
+}
+
+impl std::error::Error for SeqOverflow {}
+
+/*index of an ordered event log keyed by sequence number
+ * append hands out the next number and goes down the right edge of a time series tree,
+ * insert_at takes entries from elsewhere (e.g. a replica catching up) out of order,
+ * which is what leaves gaps behind until the missing ones arrive
+ */
+#[derive(Debug)]
+pub struct LogMap<V> {
+    map: BTreeMap<u64, V>,
+    next_seq: u64,
+}
+
+impl<V> LogMap<V> {
+    pub fn new(min_degree: usize) -> Self {
+        LogMap {
+            map: BTreeMap::new_time_series(min_degree),
+            next_seq: 0,
+        }
+    }
+
+    /*store `value` under the next sequence number and return it
+     * panics once the numbers run out, i.e. when the next one would be u64::MAX
+     */
+    pub fn append(&mut self, value: V) -> u64 {
+        let seq = self.next_seq;
+        assert!(seq < u64::MAX, "LogMap ran out of sequence numbers");
+        self.map.push_back(seq, value);
+        self.next_seq = seq + 1;
+        seq
+    }
+
+    /*store `value` under `seq` wherever it falls, append continues after the largest number
+     * seen so far, returns the value that was replaced
+     * u64::MAX is refused, append would have no number left after it
+     */
+    pub fn insert_at(&mut self, seq: u64, value: V) -> Result<Option<V>, SeqOverflow> {
+        let next = seq.checked_add(1).ok_or(SeqOverflow)?;
+        self.next_seq = self.next_seq.max(next);
+        Ok(self.map.insert(seq, value))
+    }
+
+    pub fn get(&self, seq: u64) -> Option<&V> {
+        self.map.get(&seq)
+    }
+
+    /*the number the next append gets*/
+    pub fn next_seq(&self) -> u64 {
+        self.next_seq
+    }
+
+    pub fn first_seq(&self) -> Option<u64> {
+        self.map.first_key().copied()
+    }
+
+    pub fn last_seq(&self) -> Option<u64> {
+        self.map.last_key().copied()
+    }
+
+    /*the last `n` entries, oldest first*/
+    pub fn tail(&self, n: usize) -> Vec<(u64, &V)> {
+        let mut tail: Vec<(u64, &V)> = self
+            .map
+            .iter_rev()
+            .take(n)
+            .map(|(seq, value)| (*seq, value))
+            .collect();
+        tail.reverse();
+        tail
+    }
+
+    pub fn range<R: RangeBounds<u64>>(&self, range: R) -> impl Iterator<Item = (u64, &V)> {
+        self.map.range(range).map(|(seq, value)| (*seq, value))
+    }
+
+    /*retention: drop every entry before `seq`, append numbering carries on where it was*/
+    pub fn trim_before(&mut self, seq: u64) {
+        self.map.trim_before(&seq);
+    }
+
+    /*the runs of sequence numbers missing between the first and the last entry,
+     * found by a scan of the keys
+     */
+    pub fn gaps(&self) -> Vec<Range<u64>> {
+        let mut gaps = Vec::new();
+        let mut keys = self.map.keys();
+        let Some(&first) = keys.next() else {
+            return gaps;
+        };
+
+        /*no entry sits at u64::MAX, saturating only keeps this from ever wrapping*/
+        let mut expected = first.saturating_add(1);
+        for &seq in keys {
+            if seq != expected {
+                gaps.push(expected..seq);
+            }
+            expected = seq.saturating_add(1);
+        }
+        gaps
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sequence_numbers_at_the_top_of_u64() {
+        let mut log = LogMap::new(2);
+        assert_eq!(log.insert_at(u64::MAX, "x"), Err(SeqOverflow));
+        assert!(log.is_empty());
+
+        log.insert_at(u64::MAX - 3, "a").unwrap();
+        log.insert_at(u64::MAX - 1, "b").unwrap();
+        assert_eq!(log.next_seq(), u64::MAX);
+        assert_eq!(log.gaps(), vec![u64::MAX - 2..u64::MAX - 1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "ran out of sequence numbers")]
+    fn append_past_the_last_sequence_number_panics() {
+        let mut log = LogMap::new(2);
+        log.insert_at(u64::MAX - 1, "last").unwrap();
+        log.append("one too many");
+    }
+}
@@ -1,153 +1,787 @@
-/*A BTreeMap implementation
- * the data structure is commonly used in database building
- * to optomize query searching nistead of a normal HashMap as used in std::collections::HashMap;
- * BTreeMap store the key Values in sorted order
- *For more reference check out std:collections::BTreeMap;
- * September 25, 2024
+/*demo of the btreemap crate, every part of the API gets a short sample run*/
+
+use btreemap::{
+    count_comparisons, freeze_to_rust_source, BTreeMap, Counted, DiskBTreeMap, DynBucket, Entry,
+    EntryTooLarge, Every, FixedBTreeMap, FrozenMap, HandleMap, HistoryMap, HotRangeTracker,
+    KeyCodec, LogMap, Maintenance, ManualClock, MemoryPages, MergeIter, NodeInfo, OverflowPolicy,
+    RangeLocks, Recorder, ResumeToken, SoftDeleteMap, SplitStrategy, StorageError, TreeVisitor,
+    VersionedBTreeMap, WeakValueMap,
+};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/*lookup table frozen into the binary, a build script would include! what
+ * freeze_to_rust_source wrote instead of spelling it out
  */
+static HTTP_STATUS: FrozenMap<u16, &str> =
+    FrozenMap::new(&[(200, "OK"), (301, "Moved Permanently"), (404, "Not Found")]);
 
-#[derive(Debug, Clone)]
-struct BTreeNode<K, V> {
-    keys: Vec<K>,
-    values: Vec<V>,
-    children: Vec<Box<BTreeNode<K, V>>>,
-    is_leaf: bool,
-    min_degree: usize,
-}
+/*global registry without any lazy initialization*/
+static SERVICES: Mutex<BTreeMap<&str, u16>> = Mutex::new(BTreeMap::EMPTY);
 
-impl<K: Ord + Clone, V: Clone> BTreeNode<K, V> {
-    fn new(min_degree: usize, is_leaf: bool) -> Self {
-        BTreeNode {
-            keys: Vec::new(),
-            values: Vec::new(),
-            children: Vec::new(),
-            is_leaf,
-            min_degree,
-        }
+fn main() {
+    /*test samples*/
+    let mut btree = BTreeMap::new(2); // Minimum degree of 2
+    btree.insert(10, "Ten");
+    btree.insert(20, "Twenty");
+    btree.insert(5, "Five");
+    btree.insert(6, "Six");
+    btree.insert(12, "Twelve");
+    println!("Replaced: {:?}", btree.insert(20, "twenty"));
+
+    if let Some(value) = btree.get(&10) {
+        println!("Found: {}", value);
+    } else {
+        println!("Not found");
+    }
+
+    if let Some(value) = btree.get(&7) {
+        println!("Found: {}", value);
+    } else {
+        println!("Not found");
+    }
+
+    println!("Removed: {:?}", btree.remove(&6));
+    println!("Removed again: {:?}", btree.remove(&6));
+    println!(
+        "{} entries, has 12: {}, first {:?}, last {:?}",
+        btree.len(),
+        btree.contains_key(&12),
+        btree.first_key_value(),
+        btree.last_key_value()
+    );
+    if let Some(value) = btree.get_mut(&12) {
+        *value = "TWELVE";
+    }
+    btree.clear();
+    println!("after clear: empty {}", btree.is_empty());
+
+    #[cfg(feature = "telemetry")]
+    {
+        let metrics = btree.metrics();
+        println!(
+            "{} inserts, p99 {:?}; {} searches, p99 {:?}; {} removes; {} ranges",
+            metrics.insert.count(),
+            metrics.insert.percentile(0.99),
+            metrics.search.count(),
+            metrics.search.percentile(0.99),
+            metrics.remove.count(),
+            metrics.range.count()
+        );
     }
 
-    fn insert_non_full(&mut self, key: K, value: V) {
-        let pos = self
-            .keys
+    let mut versioned = VersionedBTreeMap::new(2);
+    let version = versioned.insert("config", 1);
+    versioned.insert_with_version("replica", 7, 100).unwrap();
+    if let Some((value, version)) = versioned.get_versioned(&"replica") {
+        println!("replica = {} @ v{}", value, version);
+    }
+    match versioned.compare_and_set(&"config", version, 2) {
+        Ok(version) => println!("config updated @ v{}", version),
+        Err(current) => println!("config conflict, current {:?}", current),
+    }
+    println!(
+        "written after v1: {:?}",
+        versioned.changed_since(1).collect::<Vec<_>>()
+    );
+
+    let mut sessions = HandleMap::new(2);
+    let alice = sessions.insert("alice", 1);
+    sessions.remove(&"alice");
+    sessions.insert("alice", 2);
+    let fresh = sessions.handle(&"alice").unwrap();
+    println!(
+        "old handle: {:?}, new handle: {:?}",
+        sessions.get_by_handle(&alice),
+        sessions.get_by_handle(&fresh)
+    );
+    sessions.clear();
+    println!("after clear: {:?}", sessions.get_by_handle(&fresh));
+
+    let mut registry = DynBucket::new(2);
+    registry.insert("port", 8080u16);
+    registry.insert("host", String::from("localhost"));
+    println!(
+        "registry: port {:?}, port as a string {:?} (it is a {:?}), host {:?}",
+        registry.get::<u16>(&"port"),
+        registry.get::<String>(&"port"),
+        registry.type_name(&"port"),
+        registry.get::<String>(&"host")
+    );
+
+    let mut settings = HistoryMap::new(2, 2);
+    for timeout in [10, 20, 30, 40] {
+        settings.insert("timeout", timeout);
+    }
+    println!(
+        "timeout is {:?}, before that {:?}",
+        settings.get(&"timeout"),
+        settings.history(&"timeout").collect::<Vec<_>>()
+    );
+
+    let mut events = LogMap::new(2);
+    for event in ["login", "view", "logout"] {
+        events.append(event);
+    }
+    events.insert_at(6, "login").unwrap();
+    events.trim_before(1);
+    println!(
+        "event log: last two {:?}, missing {:?}",
+        events.tail(2),
+        events.gaps()
+    );
+
+    let mut metrics = BTreeMap::new_time_series(2);
+    for timestamp in 0..100 {
+        metrics.insert(timestamp, timestamp * 10);
+    }
+    metrics.trim_before(&90);
+    println!(
+        "after retention: 89 -> {:?}, 95 -> {:?}",
+        metrics.get(&89),
+        metrics.get(&95)
+    );
+    println!(
+        "time series tree is valid: {:?}",
+        metrics.check_invariants()
+    );
+    for (depth, nodes) in metrics.occupancy_histogram().iter().enumerate() {
+        println!("depth {}: nodes by key count {:?}", depth, nodes);
+    }
+
+    let mut countdown = BTreeMap::new(2);
+    countdown.set_split_strategy(SplitStrategy::LeftBiased);
+    for tick in (0..100).rev() {
+        countdown.insert(tick, ());
+    }
+    println!(
+        "descending inserts with {:?} splits: {} leaves, valid: {:?}",
+        countdown.split_strategy(),
+        countdown
+            .occupancy_histogram()
+            .last()
+            .unwrap()
             .iter()
-            .position(|k| *k >= key)
-            .unwrap_or(self.keys.len());
-
-        if self.is_leaf {
-            self.keys.insert(pos, key);
-            self.values.insert(pos, value);
-        } else {
-            if self.children[pos].keys.len() == 2 * self.min_degree - 1 {
-                self.split_child(pos);
-                if key > self.keys[pos] {
-                    self.children[pos + 1].insert_non_full(key, value);
-                } else {
-                    self.children[pos].insert_non_full(key, value);
-                }
-            } else {
-                self.children[pos].insert_non_full(key, value);
-            }
-        }
+            .sum::<usize>(),
+        countdown.check_invariants()
+    );
+
+    let mut events = BTreeMap::new(2);
+    events.set_adaptive_inserts(true);
+    for id in 0..100 {
+        events.insert(id, ());
     }
+    println!(
+        "adaptive inserts after 100 ascending ids: {:?}",
+        events.insert_stats()
+    );
 
-    fn split_child(&mut self, index: usize) {
-        let min_degree = self.min_degree;
-        let mut new_child = Box::new(BTreeNode::new(min_degree, self.children[index].is_leaf));
+    let squares = BTreeMap::from_sorted_vec(2, (0..50).map(|n| (n, n * n)).collect());
+    println!("7 squared is {:?}", squares.get(&7));
+    let cubes: BTreeMap<_, _> = (0..50)
+        .map(|n| (n, n * n * n))
+        .collect::<std::collections::BTreeMap<_, _>>()
+        .into();
+    println!("3 cubed is {:?}", cubes.get(&3));
+    println!(
+        "first cubes: {:?}",
+        cubes.into_iter().take(3).collect::<Vec<_>>()
+    );
+    println!(
+        "largest squares: {:?}",
+        squares.iter_rev().take(3).collect::<Vec<_>>()
+    );
+    println!(
+        "keys 20..=10: {:?}",
+        squares
+            .range_rev(10..=20)
+            .map(|(k, _)| k)
+            .collect::<Vec<_>>()
+    );
+    println!("last key: {:?}", squares.keys_rev().next());
+    println!(
+        "smallest squares: {:?}",
+        squares.values().take(3).collect::<Vec<_>>()
+    );
+    println!(
+        "keys 5..9: {:?}",
+        squares.range(5..9).map(|(k, _)| k).collect::<Vec<_>>()
+    );
+    let mut scan = squares.range(5..40);
+    let before = scan.next().map(|(k, _)| *k);
+    scan.seek(&30);
+    println!(
+        "scan from {:?}, then seek to 30: {:?}",
+        before,
+        scan.map(|(k, _)| *k).collect::<Vec<_>>()
+    );
+    println!("first key: {:?}", squares.keys().next());
+    let mut odd = 0;
+    for (n, _) in &squares {
+        odd += n % 2;
+    }
+    println!("odd keys: {}", odd);
+    let teens = squares.view(13..20);
+    println!(
+        "{} squares in the teens view, 15 -> {:?}, 25 -> {:?}, last {:?}",
+        teens.len(),
+        teens.get(&15),
+        teens.get(&25),
+        teens.iter().last()
+    );
+    println!(
+        "squares of 10..20: first {:?}, last {:?}",
+        squares.first_in_range(10..20),
+        squares.last_in_range(10..20)
+    );
+    let shuffled: BTreeMap<u32, u32> = (0..1000).map(|n| (n * 7919 % 1000, n)).collect();
+    let mut inserted = BTreeMap::new(shuffled.min_degree());
+    for n in 0..1000 {
+        inserted.insert(n * 7919 % 1000, n);
+    }
+    let fill = |map: &BTreeMap<u32, u32>| {
+        let nodes: usize = map.occupancy_histogram().iter().flatten().sum();
+        map.len() as f64 / (nodes * (2 * map.min_degree() - 1)) as f64
+    };
+    println!(
+        "fill factor: collected {:.2}, inserted one by one {:.2}",
+        fill(&shuffled),
+        fill(&inserted)
+    );
+    let mut evens = BTreeMap::from_sorted_iter(2, (0..10).map(|n| (n * 2, ())));
+    evens.extend([(1, ()), (3, ()), (2, ())]);
+    println!("extended: {:?}", evens.keys().collect::<Vec<_>>());
+    let parity = BTreeMap::from_sorted_vec(2, (0..10).map(|n| (n, n % 2 == 0)).collect());
+    println!("even numbers: {:?}", parity.invert().get(&true));
+    for (tens, members) in squares.group_by(|n| n / 10) {
+        println!("{}x: {} squares", tens, members.len());
+    }
+    println!(
+        "squares 10..30: ~{:?} (entries, bytes)",
+        squares.estimate_range_size(10..30)
+    );
+    println!(
+        "median key ~{:?}, p95 key ~{:?}",
+        squares.quantile(0.5),
+        squares.quantile(0.95)
+    );
+    let sum: i32 = squares
+        .chunks()
+        .map(|(_, values)| values.iter().sum::<i32>())
+        .sum();
+    println!("sum of squares below 50: {}", sum);
 
-        let child = &mut self.children[index];
-        new_child.keys.extend(child.keys.split_off(min_degree));
-        new_child.values.extend(child.values.split_off(min_degree));
-        if !child.is_leaf {
-            new_child
-                .children
-                .extend(child.children.split_off(min_degree));
-        }
+    let mut sessions = WeakValueMap::new(2);
+    let alice = Arc::new("alice's session");
+    let bob = Arc::new("bob's session");
+    sessions.insert(1, &alice);
+    sessions.insert(2, &bob);
+    drop(bob);
+    println!(
+        "session 1: {:?}, session 2: {:?}, pruned {}",
+        sessions.get_strong(&1),
+        sessions.get_strong(&2),
+        sessions.prune()
+    );
 
-        self.keys.insert(index, child.keys.pop().unwrap());
-        self.values.insert(index, child.values.pop().unwrap());
-        self.children.insert(index + 1, new_child);
+    /*shared keys: the tree holds reference counted keys, lookups borrow them as &str / &[u8]*/
+    let mut users: BTreeMap<Arc<str>, u32> = BTreeMap::new(2);
+    users.insert(Arc::from("user:alice"), 31);
+    users.insert(Arc::from("user:bob"), 27);
+    println!("alice is {:?}", users.get("user:alice"));
+    let mut blobs: BTreeMap<Arc<[u8]>, &str> = BTreeMap::new(2);
+    blobs.insert(Arc::from(&b"\x00\x01"[..]), "header");
+    println!("blob 0001 is {:?}", blobs.get(&b"\x00\x01"[..]));
+
+    SERVICES.lock().unwrap().insert("http", 80);
+    SERVICES.lock().unwrap().insert("ssh", 22);
+    println!("ssh port: {:?}", SERVICES.lock().unwrap().get("ssh"));
+
+    let mut recorder = Recorder::new(BTreeMap::new(2), Vec::new());
+    for (id, name) in [(1u32, "one"), (2, "two"), (3, "three")] {
+        recorder.insert(id, name.to_string()).unwrap();
     }
-}
+    recorder.trim_before(&2).unwrap();
+    recorder.remove(&2).unwrap();
+    println!("recorded 3 -> {:?}", recorder.map().get(&3));
+    let (_, log) = recorder.into_parts();
+    let mut replayed: BTreeMap<u32, String> = BTreeMap::new(2);
+    replayed.replay(&log[..]).unwrap();
+    println!(
+        "replayed {} bytes: 1 -> {:?}, 3 -> {:?}",
+        log.len(),
+        replayed.get(&1),
+        replayed.get(&3)
+    );
 
-#[derive(Debug)]
-struct BTreeMap<K, V> {
-    root: Option<BTreeNode<K, V>>,
-    min_degree: usize,
-}
+    let mut hits = BTreeMap::new(2);
+    hits.set_merge_operator(|total, delta| *total += delta);
+    let mut recorder = Recorder::new(hits, Vec::new());
+    for _ in 0..3 {
+        recorder.merge("/index".to_string(), 1u64).unwrap();
+    }
+    let (_, log) = recorder.into_parts();
+    let mut replayed: BTreeMap<String, u64> = BTreeMap::new(2);
+    replayed.set_merge_operator(|total, delta| *total += delta);
+    replayed.replay(&log[..]).unwrap();
+    println!("replayed hits: {:?}", replayed.get("/index"));
 
-impl<K: Ord + Clone, V: Clone> BTreeMap<K, V> {
-    fn new(min_degree: usize) -> Self {
-        BTreeMap {
-            root: None,
-            min_degree,
-        }
+    let mut primary = Recorder::new(BTreeMap::<u64, String>::new(2), Vec::new());
+    let mut follower: BTreeMap<u64, String> = BTreeMap::new(2);
+    primary.insert(1, "first".to_string()).unwrap();
+    follower.apply_oplog(&primary.export_oplog()[..]).unwrap();
+    primary.insert(2, "second".to_string()).unwrap();
+    follower.apply_oplog(&primary.export_oplog()[..]).unwrap();
+    println!("follower caught up: 2 -> {:?}", follower.get(&2));
+
+    let mut encoded: Vec<(Vec<u8>, (i32, String))> =
+        [(-2, "b"), (-2, "a\0"), (7, "a"), (-300, "z")]
+            .into_iter()
+            .map(|(n, s)| {
+                let key = (n, s.to_string());
+                (key.to_key_bytes(), key)
+            })
+            .collect();
+    encoded.sort();
+    println!(
+        "keys by encoded bytes: {:?}, first decodes back to {:?}",
+        encoded.iter().map(|(_, key)| key).collect::<Vec<_>>(),
+        <(i32, String)>::decode_key(&mut &encoded[0].0[..])
+    );
+
+    let path = std::env::temp_dir().join("btreemap-demo.pages");
+    let _ = std::fs::remove_file(&path);
+    let mut index = DiskBTreeMap::open_with_min_degree(&path, 4).unwrap();
+    for id in 0..100u32 {
+        index.insert(id, format!("row {}", id)).unwrap();
+    }
+    index.flush().unwrap();
+    drop(index);
+    let mut index: DiskBTreeMap<u32, String> = DiskBTreeMap::open(&path).unwrap();
+    let cold = index
+        .get_with_deadline(&5, std::time::Instant::now())
+        .unwrap_err();
+    println!("lookup on a cold cache with no time left: {}", cold.kind());
+    if let Some(context) = cold
+        .get_ref()
+        .and_then(|e| e.downcast_ref::<StorageError>())
+    {
+        println!(
+            "{} of key {:?} stopped at page {} (depth {:?})",
+            context.operation(),
+            context.key(),
+            context.page_id(),
+            context.depth()
+        );
     }
+    println!(
+        "warmed 40..50 from {} pages, the rest of the tree from {} more",
+        index.warm(40..50).unwrap(),
+        index.warm(..).unwrap()
+    );
+    index.reset_io_stats();
+    index.get(&77).unwrap();
+    let stats = index.io_stats();
+    println!(
+        "lookup of 77 on a warm cache: {} hits, {} pages read, hit rate {}",
+        stats.cache_hits,
+        stats.page_reads,
+        stats.hit_rate()
+    );
+    println!(
+        "reopened {} entries from disk, 42 -> {:?}",
+        index.len(),
+        index.get(&42).unwrap()
+    );
+    index.insert(7, "x".repeat(10_000)).unwrap();
+    index.close().unwrap();
+    let mut index: DiskBTreeMap<u32, String> = DiskBTreeMap::open(&path).unwrap();
+    println!(
+        "value spilled to overflow pages read back with {:?} bytes, closed cleanly: {}",
+        index.get(&7).unwrap().map(String::len),
+        index.opened_clean()
+    );
+    index.set_size_limits(index.size_limits().0, 1024);
+    let err = index.insert(8, "x".repeat(4000)).unwrap_err();
+    if let Some(too_large) = err
+        .get_ref()
+        .and_then(|err| err.downcast_ref::<EntryTooLarge>())
+    {
+        println!("rejected: {}", too_large);
+    }
+    let backup = std::env::temp_dir().join("btreemap-demo-backup.pages");
+    let _ = std::fs::remove_file(&backup);
+    index.backup_to(&backup).unwrap();
+    index.close().unwrap();
+    std::fs::remove_file(&path).unwrap();
+    let mut restored: DiskBTreeMap<u32, String> = DiskBTreeMap::open(&backup).unwrap();
+    println!(
+        "backup holds {} entries, 42 -> {:?}",
+        restored.len(),
+        restored.get(&42).unwrap()
+    );
+    restored.close().unwrap();
+    std::fs::remove_file(&backup).unwrap();
 
-    fn insert(&mut self, key: K, value: V) {
-        if self.root.is_none() {
-            self.root = Some(BTreeNode::new(self.min_degree, true));
-        }
+    let archive = std::env::temp_dir().join("btreemap-demo.archive");
+    let prices = BTreeMap::from_sorted_iter(2, (1..=20u32).map(|id| (id, format!("${}", id * 3))));
+    prices.export_archive(&archive).unwrap();
+    let imported: BTreeMap<u32, String> = BTreeMap::import_archive(&archive).unwrap();
+    println!(
+        "imported {} prices from the archive, 7 -> {:?}",
+        imported.len(),
+        imported.get(&7)
+    );
+    let repriced = std::env::temp_dir().join("btreemap-demo-repriced.archive");
+    let mut updated = imported;
+    updated.insert(7, "$20".to_string());
+    updated.remove(&1);
+    updated.insert(21, "$63".to_string());
+    updated.export_archive(&repriced).unwrap();
+    let audit = BTreeMap::<u32, String>::diff_snapshots(&archive, &repriced, true).unwrap();
+    println!(
+        "repricing added {}, removed {}, changed {}: {:?}",
+        audit.added, audit.removed, audit.changed, audit.changes[1]
+    );
+    std::fs::remove_file(&repriced).unwrap();
+    std::fs::remove_file(&archive).unwrap();
+
+    let locks = RangeLocks::new();
+    let window = locks.lock_range(100..200);
+    println!(
+        "150..250 free while 100..200 is held: {}, 200..300 free: {}",
+        locks.try_lock_range(150..250).is_some(),
+        locks.try_lock_range(200..300).is_some()
+    );
+    drop(window);
+    let _window = locks.lock_range(150..250);
+
+    let mut sensors: FixedBTreeMap<u8, i16, 4> = FixedBTreeMap::new();
+    for (id, reading) in [(3, 210), (1, 198), (4, 225), (2, 201)] {
+        sensors.insert(id, reading).unwrap();
+    }
+    if let Err(full) = sensors.insert(5, 230) {
+        println!("sensor table full, dropped {} = {}", full.key, full.value);
+    }
+    sensors.remove(&1);
+    let mut window: FixedBTreeMap<u32, &str, 3> =
+        FixedBTreeMap::with_policy(OverflowPolicy::EvictSmallest);
+    for (seq, event) in [(1, "boot"), (2, "link up"), (3, "sample"), (4, "sample")] {
+        window.insert(seq, event).unwrap();
+    }
+    println!(
+        "latest 3 events: {:?}",
+        window.range(..).collect::<Vec<_>>()
+    );
+    println!(
+        "{} sensors, #3 reads {:?}, 2..=3: {:?}",
+        sensors.len(),
+        sensors.get(&3),
+        sensors.range(2..=3).collect::<Vec<_>>()
+    );
+
+    let mut schedule =
+        BTreeMap::from_sorted_vec(2, (1..=20).map(|slot| (slot, slot * 100)).collect());
+    schedule.swap(&3, &17);
+    schedule.update_many(&[20, 1, 5, 99], |_, minutes| *minutes += 15);
+    println!(
+        "after swapping slots 3 and 17: {:?} {:?}, slot 5 delayed to {:?}",
+        schedule.get(&3),
+        schedule.get(&17),
+        schedule.get(&5)
+    );
+    println!(
+        "cancelled slots 2, 99 and 7: {:?}, {} slots left",
+        schedule.remove_many(&[2, 99, 7]),
+        schedule.len()
+    );
+
+    let mut words = BTreeMap::new(2);
+    for word in "the cat and the dog and the bird".split(' ') {
+        *words.entry(word).or_insert(0) += 1;
+    }
+    words
+        .entry("cat")
+        .and_modify(|count| *count *= 10)
+        .or_default();
+    if let Entry::Occupied(dog) = words.entry("dog") {
+        let (word, count) = dog.remove_entry();
+        println!("removed {} = {}", word, count);
+    }
+    println!("word counts: {:?}", words.iter().collect::<Vec<_>>());
+
+    let mut counters = BTreeMap::new_auto();
+    counters.set_merge_operator(|total, delta| *total += delta);
+    for page in ["home", "about", "home", "home"] {
+        counters.merge(page, 1);
+    }
+    println!(
+        "home visited {:?} times (min_degree {})",
+        counters.get(&"home"),
+        counters.min_degree()
+    );
 
-        if let Some(ref mut root) = self.root {
-            if root.keys.len() == 2 * self.min_degree - 1 {
-                let mut new_root = BTreeNode::new(self.min_degree, false);
-                new_root.children.push(Box::new(root.clone()));
-                new_root.split_child(0);
-                new_root.insert_non_full(key, value);
-                self.root = Some(new_root);
-            } else {
-                root.insert_non_full(key, value);
+    let readings = Arc::new(Mutex::new(BTreeMap::new_time_series(2)));
+    let mut maintenance = Maintenance::new(Arc::clone(&readings));
+    maintenance.schedule(
+        "retention",
+        Every(Duration::from_secs(60)),
+        |readings: &mut BTreeMap<u64, f32>| {
+            if let Some(&newest) = readings.last_key() {
+                readings.trim_before(&newest.saturating_sub(10));
             }
-        }
+        },
+    );
+    for second in 0..30 {
+        readings
+            .lock()
+            .unwrap()
+            .insert(second, 20.0 + second as f32 / 10.0);
     }
+    let ran = maintenance.tick();
+    println!(
+        "maintenance ran {} task(s), {} readings kept, {} due right after",
+        ran,
+        readings.lock().unwrap().len(),
+        maintenance.tick()
+    );
+
+    let clock = ManualClock::new();
+    let mut simulated = Maintenance::with_clock(Arc::clone(&readings), clock.clone());
+    simulated.schedule(
+        "compact",
+        Every(Duration::from_secs(60)),
+        |_: &mut BTreeMap<u64, f32>| {},
+    );
+    let first = simulated.tick();
+    clock.advance(Duration::from_secs(59));
+    let early = simulated.tick();
+    clock.advance(Duration::from_secs(1));
+    println!(
+        "simulated minute: {} task(s) at 0s, {} at 59s, {} at 60s",
+        first,
+        early,
+        simulated.tick()
+    );
+
+    let mut shard = BTreeMap::from_sorted_iter(2, (0..100).map(|key| (key, key * key)));
+    let mut upper = shard.split_off(&60);
+    println!(
+        "split at 60: {} and {} entries, upper starts at {:?}",
+        shard.len(),
+        upper.len(),
+        upper.first_key_value()
+    );
+    shard.append(&mut upper);
+    println!(
+        "appended back: {} entries, {} left in the other shard",
+        shard.len(),
+        upper.len()
+    );
+    println!("cut points for 4 workers: {:?}", shard.partition_points(4));
+    let second_worker = shard.split_off_at(shard.len() / 2);
+    println!(
+        "halves for two workers: {} entries up to {:?}, {} from {:?}",
+        shard.len(),
+        shard.last_key(),
+        second_worker.len(),
+        second_worker.first_key_value().map(|(key, _)| key)
+    );
 
-    fn search(&self, key: &K) -> Option<&V> {
-        self.root
-            .as_ref()
-            .and_then(|node| self.search_in_node(node, key))
+    let clock = ManualClock::new();
+    let mut tenants = HotRangeTracker::with_clock(
+        BTreeMap::new(2),
+        vec![1000, 2000, 3000],
+        Duration::from_secs(30),
+        clock.clone(),
+    );
+    for order in 0..500u32 {
+        tenants.insert(2000 + order, order);
+        tenants.get(&(order % 10));
+    }
+    clock.advance(Duration::from_secs(1));
+    tenants.sample();
+    for range in tenants.hot_ranges(2) {
+        println!(
+            "hot range {:?}..{:?}: {:.0} reads/s, {:.0} writes/s, {:.0} splits/s",
+            range.start, range.end, range.reads_per_sec, range.writes_per_sec, range.splits_per_sec
+        );
     }
 
-    /*use recursion to search the node tree*/
-    fn search_in_node<'a>(&self, node: &'a BTreeNode<K, V>, key: &K) -> Option<&'a V> {
-        let pos = node
-            .keys
-            .iter()
-            .position(|k| k >= key)
-            .unwrap_or(node.keys.len());
+    let base = BTreeMap::from_sorted_iter(2, [(1, "base"), (2, "base"), (4, "base")]);
+    let overlay = BTreeMap::from_sorted_iter(2, [(2, "overlay"), (3, "overlay")]);
+    println!(
+        "layered: {:?}",
+        MergeIter::new([base.into_iter(), overlay.into_iter()]).collect::<Vec<_>>()
+    );
 
-        if pos < node.keys.len() && &node.keys[pos] == key {
-            return Some(&node.values[pos]);
-        }
+    for min_degree in [2, 16] {
+        let ids = BTreeMap::from_sorted_iter(min_degree, (0..10_000).map(|id| (Counted(id), ())));
+        let (_, comparisons) = count_comparisons(|| ids.get(&Counted(7_777)));
+        println!(
+            "min_degree {}: {} comparisons to find a key, per level {:?}",
+            min_degree,
+            comparisons,
+            ids.comparisons_per_level(&Counted(7_777))
+        );
+    }
+
+    let words = "the quick fox and the lazy dog and the cat".split(' ');
+    let word_counts =
+        BTreeMap::from_iter_dedup(words.map(|word| (word, 1)), |count, more| *count += more);
+    println!(
+        "word counts without a HashMap: {:?}",
+        word_counts.iter().collect::<Vec<_>>()
+    );
 
-        /*if we get to this point  and the node is leaf, then key does not exist in the tree*/
-        if node.is_leaf {
-            None
-        } else {
-            node.children
-                .get(pos)
-                .and_then(|child| self.search_in_node(child, key))
+    #[cfg(feature = "fixtures")]
+    {
+        use btreemap::fixtures;
+        let hot: BTreeMap<u64, usize> = BTreeMap::from_iter_dedup(
+            fixtures::zipfian(fixtures::SMALL, 100, 1.0, 7)
+                .into_iter()
+                .map(|key| (key, 1)),
+            |count, more| *count += more,
+        );
+        let tenants: BTreeMap<String, ()> = fixtures::clustered_strings(fixtures::SMALL, 4, 7)
+            .into_iter()
+            .map(|key| (key, ()))
+            .collect();
+        println!(
+            "zipfian: key 0 drawn {:?} times, key 99 {:?}; clustered: first {:?}",
+            hot.get(&0),
+            hot.get(&99),
+            tenants.first_key_value().map(|(key, _)| key)
+        );
+    }
+
+    /*a stats pass written outside the crate: nodes per level and the fullest leaf*/
+    #[derive(Default)]
+    struct LevelStats {
+        nodes_per_level: Vec<usize>,
+        fullest_leaf: usize,
+    }
+    impl<K, V> TreeVisitor<K, V> for LevelStats {
+        fn enter_node(&mut self, node: NodeInfo) {
+            if self.nodes_per_level.len() <= node.depth {
+                self.nodes_per_level.push(0);
+            }
+            self.nodes_per_level[node.depth] += 1;
+            if node.is_leaf {
+                self.fullest_leaf = self.fullest_leaf.max(node.len);
+            }
         }
     }
-}
+    let mut stats = LevelStats::default();
+    squares.visit(&mut stats);
+    println!(
+        "nodes per level {:?}, fullest leaf holds {}",
+        stats.nodes_per_level, stats.fullest_leaf
+    );
 
-fn main() {
-    /*test samples*/
-    let mut btree = BTreeMap::new(2); // Minimum degree of 2
-    btree.insert(10, "Ten");
-    btree.insert(20, "Twenty");
-    btree.insert(5, "Five");
-    btree.insert(6, "Six");
-    btree.insert(12, "Twelve");
+    let mut trash = SoftDeleteMap::new(2);
+    for (id, name) in [(1, "notes.txt"), (2, "draft.md"), (3, "photo.png")] {
+        trash.insert(id, name);
+    }
+    trash.remove(&2);
+    trash.remove(&3);
+    trash.restore(&3);
+    println!(
+        "live files {:?}, in the trash {:?}",
+        trash.iter().collect::<Vec<_>>(),
+        trash.deleted().collect::<Vec<_>>()
+    );
+    println!("purged {}, {} files left", trash.purge(), trash.len());
 
-    if let Some(value) = btree.search(&10) {
-        println!("Found: {}", value);
-    } else {
-        println!("Not found");
+    let mut seats = BTreeMap::from_sorted_vec(2, (1..=10).map(|seat| (seat, "free")).collect());
+    let mut booking = seats.edit_range(4..=6);
+    for seat in 4..=6 {
+        booking.insert(seat, "booked").unwrap();
     }
+    println!(
+        "seat 9 is outside the booking: {:?}",
+        booking.insert(9, "booked").is_err()
+    );
+    drop(booking);
+    println!("after a dropped booking seat 5 is {:?}", seats.get(&5));
+    let mut booking = seats.edit_range(4..=6);
+    booking.insert(5, "booked").unwrap();
+    booking.remove(&6);
+    booking.commit();
+    println!(
+        "after a committed booking: seat 5 {:?}, seat 6 {:?}",
+        seats.get(&5),
+        seats.get(&6)
+    );
 
-    if let Some(value) = btree.search(&7) {
-        println!("Found: {}", value);
-    } else {
-        println!("Not found");
+    println!(
+        "frozen table: 404 -> {:?}, {} statuses",
+        HTTP_STATUS.get(&404),
+        HTTP_STATUS.len()
+    );
+    let table = std::env::temp_dir().join("btreemap-demo-table.rs");
+    freeze_to_rust_source([("teapot", 418), ("ok", 200)], &table).unwrap();
+    println!(
+        "generated table source:\n{}",
+        std::fs::read_to_string(&table).unwrap().trim_end()
+    );
+    std::fs::remove_file(&table).unwrap();
+
+    /*pages of 3 users per request, the client only keeps the token between them*/
+    let users = BTreeMap::from_sorted_vec(2, (1..=8u32).map(|id| (id, id * 11)).collect());
+    let first_page: Vec<_> = users.iter().take(3).collect();
+    let token = users.resume_token(first_page.last().unwrap().0).to_string();
+    let token: ResumeToken = token.parse().unwrap();
+    println!(
+        "first page {:?}, next page after token {} is {:?}",
+        first_page,
+        token,
+        users
+            .resume_from(&token)
+            .unwrap()
+            .take(3)
+            .collect::<Vec<_>>()
+    );
+
+    /*one buffer for every query of the loop*/
+    let readings = BTreeMap::from_sorted_vec(2, (0..100u32).map(|t| (t, t % 7)).collect());
+    let mut buffer = Vec::with_capacity(10);
+    for start in [10, 50, 90] {
+        readings.read_range_into(start..start + 10, &mut buffer);
     }
+    let mut slots = [(0, 0); 4];
+    let filled = readings.read_range_into_slice(96.., &mut slots);
+    println!(
+        "last scan {:?}..={:?} in a buffer of {}, {} slots filled: {:?}",
+        buffer.first(),
+        buffer.last(),
+        buffer.capacity(),
+        filled,
+        slots
+    );
+
+    let pages = MemoryPages::new();
+    let mut scratch: DiskBTreeMap<u32, String, MemoryPages> =
+        DiskBTreeMap::open_store(pages.clone(), 4).unwrap();
+    for id in 0..20 {
+        scratch.insert(id, format!("job {}", id)).unwrap();
+    }
+    scratch.close().unwrap();
+    let mut scratch: DiskBTreeMap<u32, String, MemoryPages> =
+        DiskBTreeMap::open_store(pages, 4).unwrap();
+    println!(
+        "reopened from memory pages: {} jobs, 13 -> {:?}",
+        scratch.len(),
+        scratch.get(&13).unwrap()
+    );
+
+    let stats = readings.range_stats(20..40);
+    println!(
+        "planner stats for 20..40: {} entries, {} bytes, keys {:?}..={:?}, estimate {:?}",
+        stats.entries,
+        stats.bytes,
+        stats.min_key,
+        stats.max_key,
+        readings.estimate_range_size(20..40)
+    );
 }
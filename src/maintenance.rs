@@ -0,0 +1,156 @@
+use crate::{Clock, SystemClock};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/*decides when a task is due, given the time now and when the task last ran*/
+pub trait Policy: Send {
+    fn due(&mut self, now: Instant, last_run: Option<Instant>) -> bool;
+}
+
+/*run on the first tick and then whenever `0` has passed since the last run*/
+#[derive(Debug, Clone, Copy)]
+pub struct Every(pub Duration);
+
+impl Policy for Every {
+    fn due(&mut self, now: Instant, last_run: Option<Instant>) -> bool {
+        last_run.is_none_or(|last| now.duration_since(last) >= self.0)
+    }
+}
+
+/*any closure works as a policy, e.g. "only when the map grew past a size" via shared state*/
+impl<F: FnMut(Instant, Option<Instant>) -> bool + Send> Policy for F {
+    fn due(&mut self, now: Instant, last_run: Option<Instant>) -> bool {
+        self(now, last_run)
+    }
+}
+
+struct Task<T> {
+    name: String,
+    policy: Box<dyn Policy>,
+    run: Box<dyn FnMut(&mut T) + Send>,
+    last_run: Option<Instant>,
+}
+
+/*housekeeping for anything behind a mutex: retention trims, pruning, flushes, rebuilds
+ * nothing runs on its own, tick() runs the tasks that are due right now, so embedders
+ * without a runtime call it from their own loop, spawn() moves the scheduler to a
+ * thread that ticks at a fixed period
+ * every task holds the mutex while it runs, tasks don't overlap
+ */
+pub struct Maintenance<T> {
+    target: Arc<Mutex<T>>,
+    tasks: Vec<Task<T>>,
+    clock: Box<dyn Clock>,
+}
+
+impl<T> std::fmt::Debug for Maintenance<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Maintenance")
+            .field(
+                "tasks",
+                &self.tasks.iter().map(|task| &task.name).collect::<Vec<_>>(),
+            )
+            .field("clock", &self.clock)
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> Maintenance<T> {
+    pub fn new(target: Arc<Mutex<T>>) -> Self {
+        Self::with_clock(target, SystemClock)
+    }
+
+    /*policies see the time of `clock`, e.g. a ManualClock to step through a schedule
+     * in a test by advancing it between ticks, spawn() still waits in real time
+     */
+    pub fn with_clock<C: Clock + 'static>(target: Arc<Mutex<T>>, clock: C) -> Self {
+        Maintenance {
+            target,
+            tasks: Vec::new(),
+            clock: Box::new(clock),
+        }
+    }
+
+    pub fn schedule<P, F>(&mut self, name: &str, policy: P, task: F)
+    where
+        P: Policy + 'static,
+        F: FnMut(&mut T) + Send + 'static,
+    {
+        self.tasks.push(Task {
+            name: name.to_string(),
+            policy: Box::new(policy),
+            run: Box::new(task),
+            last_run: None,
+        });
+    }
+
+    /*run every task that is due, in the order they were scheduled, returns how many ran*/
+    pub fn tick(&mut self) -> usize {
+        let mut ran = 0;
+        for task in &mut self.tasks {
+            let now = self.clock.now();
+            if !task.policy.due(now, task.last_run) {
+                continue;
+            }
+
+            let mut target = self.target.lock().unwrap_or_else(|err| err.into_inner());
+            (task.run)(&mut target);
+            task.last_run = Some(now);
+            ran += 1;
+        }
+        ran
+    }
+
+    /*tick every `period` on a background thread until the handle is stopped or dropped*/
+    pub fn spawn(mut self, period: Duration) -> MaintenanceHandle {
+        let stop = Arc::new((Mutex::new(false), Condvar::new()));
+        let signal = Arc::clone(&stop);
+        let thread = std::thread::spawn(move || {
+            let (stopped, wake) = &*signal;
+            loop {
+                self.tick();
+                let guard = stopped.lock().unwrap();
+                let (guard, _) = wake
+                    .wait_timeout_while(guard, period, |stopped| !*stopped)
+                    .unwrap();
+                if *guard {
+                    break;
+                }
+            }
+        });
+
+        MaintenanceHandle {
+            stop,
+            thread: Some(thread),
+        }
+    }
+}
+
+/*owns the background thread started by Maintenance::spawn*/
+#[derive(Debug)]
+pub struct MaintenanceHandle {
+    stop: Arc<(Mutex<bool>, Condvar)>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MaintenanceHandle {
+    /*wake the thread, let the task running right now finish and wait for the thread to exit*/
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        *self.stop.0.lock().unwrap() = true;
+        self.stop.1.notify_all();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for MaintenanceHandle {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
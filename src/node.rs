@@ -0,0 +1,943 @@
+use std::borrow::Borrow;
+use std::ops::Bound;
+
+#[derive(Debug, Clone)]
+pub(crate) struct BTreeNode<K, V> {
+    pub(crate) keys: Vec<K>,
+    pub(crate) values: Vec<V>,
+    pub(crate) children: Vec<BTreeNode<K, V>>,
+    pub(crate) is_leaf: bool,
+    pub(crate) min_degree: usize,
+}
+
+/*the first broken invariant check_invariants ran into, and where
+ * `path` are the child indices leading from the root to the node, empty for the root,
+ * so the node can be found again with a debugger or a TreeVisitor
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvariantError {
+    problem: String,
+    depth: usize,
+    path: Vec<usize>,
+}
+
+impl InvariantError {
+    fn new(problem: String, depth: usize) -> Self {
+        InvariantError {
+            problem,
+            depth,
+            path: Vec::new(),
+        }
+    }
+
+    pub fn problem(&self) -> &str {
+        &self.problem
+    }
+
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    pub fn path(&self) -> &[usize] {
+        &self.path
+    }
+}
+
+impl std::fmt::Display for InvariantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "node at depth {} (path {:?}): {}",
+            self.depth, self.path, self.problem
+        )
+    }
+}
+
+impl std::error::Error for InvariantError {}
+
+/*where a full node is split on insert
+ * a middle split leaves both halves half full, which is all the room sequential keys
+ * ever get back: ascending keys never land in the left half again, descending keys never
+ * in the right one, the biased strategies split nodes on that edge of the tree next to
+ * the new key instead, so the half that is done with stays packed and only the nodes
+ * along that edge may hold fewer keys than usual, the rest of the tree splits in the middle
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SplitStrategy {
+    #[default]
+    Middle,
+    /*for ascending keys, packs the nodes left of the right spine*/
+    RightBiased,
+    /*for descending keys, packs the nodes right of the left spine*/
+    LeftBiased,
+}
+
+impl<K: Ord, V> BTreeNode<K, V> {
+    pub(crate) fn new(min_degree: usize, is_leaf: bool) -> Self {
+        BTreeNode {
+            keys: Vec::new(),
+            values: Vec::new(),
+            children: Vec::new(),
+            is_leaf,
+            min_degree,
+        }
+    }
+
+    /*pack entries sorted by key into a tree bottom up
+     * each level is cut into as few nodes as possible with their sizes spread evenly,
+     * which keeps every node between min_degree - 1 and 2 * min_degree - 1 keys,
+     * the entries between two nodes of a level become the separators of the level above
+     */
+    pub(crate) fn from_sorted(min_degree: usize, entries: Vec<(K, V)>) -> Self {
+        let max_keys = 2 * min_degree - 1;
+        let len = entries.len();
+        let count = (len + 1).div_ceil(max_keys + 1);
+        let keys_in_leaves = len - (count - 1);
+
+        let mut entries = entries.into_iter();
+        let mut level = Vec::with_capacity(count);
+        let mut separators = Vec::with_capacity(count - 1);
+        for i in 0..count {
+            let size = keys_in_leaves / count + usize::from(i < keys_in_leaves % count);
+            let mut leaf = BTreeNode::new(min_degree, true);
+            for (key, value) in entries.by_ref().take(size) {
+                leaf.keys.push(key);
+                leaf.values.push(value);
+            }
+            level.push(leaf);
+            if i + 1 < count {
+                separators.push(entries.next().unwrap());
+            }
+        }
+
+        while level.len() > 1 {
+            let len = level.len();
+            let count = len.div_ceil(2 * min_degree);
+
+            let mut children = level.into_iter();
+            let mut keys = separators.into_iter();
+            level = Vec::with_capacity(count);
+            separators = Vec::with_capacity(count - 1);
+            for i in 0..count {
+                let size = len / count + usize::from(i < len % count);
+                let mut node = BTreeNode::new(min_degree, false);
+                node.children.extend(children.by_ref().take(size));
+                for (key, value) in keys.by_ref().take(size - 1) {
+                    node.keys.push(key);
+                    node.values.push(value);
+                }
+                level.push(node);
+                if i + 1 < count {
+                    separators.push(keys.next().unwrap());
+                }
+            }
+        }
+
+        level.pop().unwrap()
+    }
+
+    /*returns the value that was replaced if the key was already present
+     * `edge` says this node lies on the spine `strategy` is biased towards, see split_point
+     */
+    pub(crate) fn insert_non_full(
+        &mut self,
+        key: K,
+        value: V,
+        strategy: SplitStrategy,
+        edge: bool,
+    ) -> Option<V> {
+        let mut pos = self
+            .keys
+            .iter()
+            .position(|k| *k >= key)
+            .unwrap_or(self.keys.len());
+
+        if pos < self.keys.len() && self.keys[pos] == key {
+            return Some(std::mem::replace(&mut self.values[pos], value));
+        }
+
+        if self.is_leaf {
+            self.keys.insert(pos, key);
+            self.values.insert(pos, value);
+            return None;
+        }
+
+        if self.children[pos].keys.len() == 2 * self.min_degree - 1 {
+            let split =
+                self.children[pos].split_point(strategy, self.on_edge(strategy, edge, pos), &key);
+            self.split_child_at(pos, split);
+            match key.cmp(&self.keys[pos]) {
+                std::cmp::Ordering::Less => {}
+                /*the key was the median of the split child and just moved up here*/
+                std::cmp::Ordering::Equal => {
+                    return Some(std::mem::replace(&mut self.values[pos], value));
+                }
+                std::cmp::Ordering::Greater => pos += 1,
+            }
+        }
+        /*after a middle split the key may have landed off the spine*/
+        let edge = self.on_edge(strategy, edge, pos);
+        self.children[pos].insert_non_full(key, value, strategy, edge)
+    }
+
+    /*whether child `pos` of this node lies on the spine `strategy` packs towards*/
+    fn on_edge(&self, strategy: SplitStrategy, edge: bool, pos: usize) -> bool {
+        edge && match strategy {
+            SplitStrategy::Middle => false,
+            SplitStrategy::RightBiased => pos == self.keys.len(),
+            SplitStrategy::LeftBiased => pos == 0,
+        }
+    }
+
+    /*the key a full node moves up when it splits to make room for `key`
+     * on the spine the strategy packs towards, a key past every key of the node gets the
+     * far side nearly empty to itself, anything else splits in the middle
+     */
+    pub(crate) fn split_point(&self, strategy: SplitStrategy, edge: bool, key: &K) -> usize {
+        match strategy {
+            SplitStrategy::RightBiased
+                if edge && self.keys.last().is_some_and(|last| key > last) =>
+            {
+                self.packed_split()
+            }
+            SplitStrategy::LeftBiased
+                if edge && self.keys.first().is_some_and(|first| key < first) =>
+            {
+                self.packed_split_front()
+            }
+            _ => self.min_degree - 1,
+        }
+    }
+
+    pub(crate) fn split_child(&mut self, index: usize) {
+        self.split_child_at(index, self.min_degree - 1);
+    }
+
+    /*split the child at `index` around its key `split`:
+     * everything before it stays in the child, everything after moves to a new right sibling
+     * and the key itself moves up into this node
+     */
+    pub(crate) fn split_child_at(&mut self, index: usize, split: usize) {
+        let mut new_child = BTreeNode::new(self.min_degree, self.children[index].is_leaf);
+
+        let child = &mut self.children[index];
+        new_child.keys.extend(child.keys.split_off(split + 1));
+        new_child.values.extend(child.values.split_off(split + 1));
+        if !child.is_leaf {
+            new_child
+                .children
+                .extend(child.children.split_off(split + 1));
+        }
+
+        self.keys.insert(index, child.keys.pop().unwrap());
+        self.values.insert(index, child.values.pop().unwrap());
+        self.children.insert(index + 1, new_child);
+    }
+
+    /*append a key larger than every key in this subtree
+     * with `packed` full nodes on the right edge are split near their end so the left half
+     * stays packed instead of being left half empty, that leaves the right spine underfull,
+     * so it's only for trees whose right spine is relaxed, the others split in the middle
+     */
+    pub(crate) fn push_back(&mut self, key: K, value: V, packed: bool) {
+        if self.is_leaf {
+            self.keys.push(key);
+            self.values.push(value);
+            return;
+        }
+
+        let last = self.children.len() - 1;
+        if self.children[last].keys.len() == 2 * self.min_degree - 1 {
+            let split = if packed {
+                self.children[last].packed_split()
+            } else {
+                self.min_degree - 1
+            };
+            self.split_child_at(last, split);
+        }
+        self.children
+            .last_mut()
+            .unwrap()
+            .push_back(key, value, packed);
+    }
+
+    /*split point for a full node on the right edge: a leaf gives up only its last key
+     * (the appended key lands right next to it), an internal node keeps one key on the
+     * right so it never ends up as a lone child pointer that can't be rebalanced
+     */
+    pub(crate) fn packed_split(&self) -> usize {
+        self.keys.len() - if self.is_leaf { 1 } else { 2 }
+    }
+
+    /*mirror image of packed_split for a full node on the left edge: a leaf keeps none of
+     * its keys on the left (the prepended key lands there), an internal node keeps one
+     */
+    pub(crate) fn packed_split_front(&self) -> usize {
+        if self.is_leaf {
+            0
+        } else {
+            1
+        }
+    }
+
+    /*drop every key smaller than `key`, whole subtrees left of the cut go at once
+     * returns how many entries were dropped
+     */
+    pub(crate) fn trim_before(&mut self, key: &K) -> usize {
+        let pos = self
+            .keys
+            .iter()
+            .position(|k| k >= key)
+            .unwrap_or(self.keys.len());
+
+        self.keys.drain(..pos);
+        self.values.drain(..pos);
+        let mut dropped = pos;
+        if !self.is_leaf {
+            dropped += self
+                .children
+                .drain(..pos)
+                .map(|child| child.len())
+                .sum::<usize>();
+            dropped += self.children[0].trim_before(key);
+        }
+        dropped
+    }
+
+    /*entries in this subtree*/
+    pub(crate) fn len(&self) -> usize {
+        self.keys.len() + self.children.iter().map(BTreeNode::len).sum::<usize>()
+    }
+
+    /*refill the nodes on the left edge after trim_before cut into them
+     * every child we step into is left with at least min_degree keys so that
+     * a merge one level further down can not make it underfull again
+     */
+    pub(crate) fn fix_left_border(&mut self) {
+        let min_degree = self.min_degree;
+        let mut node = self;
+        while !node.is_leaf {
+            if !node.keys.is_empty() {
+                let left = node.children[0].keys.len();
+                let right = node.children[1].keys.len();
+                if left + right < 2 * min_degree - 1 {
+                    node.merge_children(0);
+                } else if left < min_degree {
+                    node.steal_from_right(0, min_degree - left);
+                }
+            }
+            node = &mut node.children[0];
+        }
+    }
+
+    /*mirror image of fix_left_border for the right edge, after split_off cut into it
+     * or before a relaxed right spine stops being the right edge
+     */
+    pub(crate) fn fix_right_border(&mut self) {
+        let min_degree = self.min_degree;
+        let mut node = self;
+        while !node.is_leaf {
+            if !node.keys.is_empty() {
+                let last = node.children.len() - 1;
+                let left = node.children[last - 1].keys.len();
+                let right = node.children[last].keys.len();
+                if left + right < 2 * min_degree - 1 {
+                    node.merge_children(last - 1);
+                } else if right < min_degree {
+                    node.steal_from_left(last, min_degree - right);
+                }
+            }
+            node = node.children.last_mut().unwrap();
+        }
+    }
+
+    /*cut this subtree at `key`: every entry from `key` on moves into the returned subtree
+     * of the same height, the nodes along the cut are left as they are, possibly empty,
+     * for fix_right_border and fix_left_border to repair
+     */
+    pub(crate) fn split_off<Q>(&mut self, key: &Q) -> Self
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = self
+            .keys
+            .iter()
+            .position(|k| k.borrow() >= key)
+            .unwrap_or(self.keys.len());
+
+        let mut right = BTreeNode::new(self.min_degree, self.is_leaf);
+        right.keys = self.keys.split_off(pos);
+        right.values = self.values.split_off(pos);
+        if !self.is_leaf {
+            let mut children = self.children.split_off(pos + 1);
+            children.insert(0, self.children[pos].split_off(key));
+            right.children = children;
+        }
+        right
+    }
+
+    /*split_off by position: the first `rank` entries of this subtree stay*/
+    pub(crate) fn split_off_at(&mut self, mut rank: usize) -> Self {
+        let mut pos = 0;
+        if self.is_leaf {
+            pos = rank;
+        } else {
+            while pos < self.keys.len() {
+                let below = self.children[pos].len();
+                if rank <= below {
+                    break;
+                }
+                rank -= below + 1;
+                pos += 1;
+            }
+        }
+
+        let mut right = BTreeNode::new(self.min_degree, self.is_leaf);
+        right.keys = self.keys.split_off(pos);
+        right.values = self.values.split_off(pos);
+        if !self.is_leaf {
+            let mut children = self.children.split_off(pos + 1);
+            children.insert(0, self.children[pos].split_off_at(rank));
+            right.children = children;
+        }
+        right
+    }
+
+    /*join two trees of this degree, every key of `lower` below every key of `upper`
+     * the last entry of `lower` becomes the separator and the shorter tree is hung off
+     * the facing edge of the taller one, so only the nodes along that edge change
+     */
+    pub(crate) fn join(mut lower: Self, mut upper: Self) -> Self {
+        let min_degree = lower.min_degree;
+        let (key, value) = lower.pop_last();
+        while lower.keys.is_empty() && !lower.is_leaf {
+            lower = lower.children.pop().unwrap();
+        }
+
+        /*a lower tree emptied by taking the separator is an empty leaf, a short child
+         * like any other to the fill_child below
+         */
+        let (lower_height, upper_height) = (lower.height(), upper.height());
+        let mut root = match lower_height.cmp(&upper_height) {
+            std::cmp::Ordering::Equal => {
+                let mut root = BTreeNode::new(min_degree, false);
+                root.keys.push(key);
+                root.values.push(value);
+                root.children.push(lower);
+                root.children.push(upper);
+                root.fill_child(0);
+                if root.children.len() == 2 {
+                    root.fill_child(1);
+                }
+                root
+            }
+            std::cmp::Ordering::Greater => {
+                lower.join_right(lower_height - upper_height, key, value, upper);
+                lower
+            }
+            std::cmp::Ordering::Less => {
+                upper.join_left(upper_height - lower_height, key, value, lower);
+                upper
+            }
+        };
+
+        if root.keys.is_empty() {
+            root = root.children.pop().unwrap();
+        } else if root.keys.len() > 2 * min_degree - 1 {
+            let old_root = std::mem::replace(&mut root, BTreeNode::new(min_degree, false));
+            root.children.push(old_root);
+            root.split_child(0);
+        }
+        root
+    }
+
+    /*hang `other`, `depth` levels shorter than this node, off the right edge*/
+    fn join_right(&mut self, depth: usize, key: K, value: V, other: Self) {
+        let last = self.children.len() - 1;
+        if depth > 1 {
+            self.children[last].join_right(depth - 1, key, value, other);
+            if self.children[last].keys.len() > 2 * self.min_degree - 1 {
+                self.split_child(last);
+            }
+            return;
+        }
+
+        self.keys.push(key);
+        self.values.push(value);
+        self.children.push(other);
+        self.fill_child(last + 1);
+    }
+
+    /*hang `other`, `depth` levels shorter than this node, off the left edge*/
+    fn join_left(&mut self, depth: usize, key: K, value: V, other: Self) {
+        if depth > 1 {
+            self.children[0].join_left(depth - 1, key, value, other);
+            if self.children[0].keys.len() > 2 * self.min_degree - 1 {
+                self.split_child(0);
+            }
+            return;
+        }
+
+        self.keys.insert(0, key);
+        self.values.insert(0, value);
+        self.children.insert(0, other);
+        self.fill_child(0);
+    }
+
+    /*bring the child at `index`, the root of a tree that was just joined in, up to
+     * min_degree - 1 keys: it merges with its neighbour when both fit in one node,
+     * otherwise it takes the keys it is missing and the neighbour still keeps enough
+     * returns where the child's keys are afterwards
+     */
+    fn fill_child(&mut self, index: usize) -> usize {
+        let min_keys = self.min_degree - 1;
+        let short = self.children[index].keys.len();
+        if short >= min_keys {
+            return index;
+        }
+
+        let neighbour = if index > 0 { index - 1 } else { index + 1 };
+        if short + self.children[neighbour].keys.len() < 2 * self.min_degree - 1 {
+            self.merge_children(index.min(neighbour));
+            return index.min(neighbour);
+        } else if index > 0 {
+            self.steal_from_left(index, min_keys - short);
+        } else {
+            self.steal_from_right(index, min_keys - short);
+        }
+        index
+    }
+
+    /*fill_child for every child after remove_sorted, however far short they were left
+     * a filled child is checked again one level down, since a child left with a single
+     * short child of its own can only be repaired once it has a neighbour again
+     */
+    fn fill_children(&mut self) {
+        let mut index = 0;
+        while index < self.children.len() {
+            if self.children.len() == 1 || self.children[index].keys.len() >= self.min_degree - 1 {
+                index += 1;
+                continue;
+            }
+            index = self.fill_child(index);
+            self.children[index].fill_children();
+        }
+    }
+
+    /*levels from this node down to the leaves, a leaf is 1*/
+    fn height(&self) -> usize {
+        let mut height = 1;
+        let mut node = self;
+        while !node.is_leaf {
+            node = &node.children[0];
+            height += 1;
+        }
+        height
+    }
+
+    fn find_mut(&mut self, key: &K) -> Option<&mut V> {
+        let pos = self
+            .keys
+            .iter()
+            .position(|k| k >= key)
+            .unwrap_or(self.keys.len());
+
+        if pos < self.keys.len() && &self.keys[pos] == key {
+            Some(&mut self.values[pos])
+        } else if self.is_leaf {
+            None
+        } else {
+            self.children[pos].find_mut(key)
+        }
+    }
+
+    /*exchange the values of two keys, low < high
+     * both keys descend together while they share a child, once their paths split
+     * the two values sit in disjoint parts of the node and can be borrowed at once
+     */
+    pub(crate) fn swap_values(&mut self, low: &K, high: &K) -> bool {
+        let find = |key: &K| {
+            let pos = self
+                .keys
+                .iter()
+                .position(|k| k >= key)
+                .unwrap_or(self.keys.len());
+            (pos, pos < self.keys.len() && &self.keys[pos] == key)
+        };
+        let (low_pos, low_here) = find(low);
+        let (high_pos, high_here) = find(high);
+
+        match (low_here, high_here) {
+            (true, true) => {
+                self.values.swap(low_pos, high_pos);
+                true
+            }
+            _ if self.is_leaf => false,
+            (false, false) if low_pos == high_pos => self.children[low_pos].swap_values(low, high),
+            (true, false) => match self.children[high_pos].find_mut(high) {
+                Some(value) => {
+                    std::mem::swap(&mut self.values[low_pos], value);
+                    true
+                }
+                None => false,
+            },
+            (false, true) => match self.children[low_pos].find_mut(low) {
+                Some(value) => {
+                    std::mem::swap(value, &mut self.values[high_pos]);
+                    true
+                }
+                None => false,
+            },
+            (false, false) => {
+                let (left, right) = self.children.split_at_mut(high_pos);
+                match (left[low_pos].find_mut(low), right[0].find_mut(high)) {
+                    (Some(a), Some(b)) => {
+                        std::mem::swap(a, b);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+        }
+    }
+
+    /*apply `f` to every entry whose key is in `keys` (sorted, no duplicates)
+     * the keys are split between the children by the separators, so each node
+     * on the way is visited once no matter how many of the keys pass through it
+     */
+    pub(crate) fn update_sorted<F: FnMut(&K, &mut V)>(&mut self, mut keys: &[&K], f: &mut F) {
+        for index in 0..=self.keys.len() {
+            let below = match self.keys.get(index) {
+                Some(separator) => keys.partition_point(|key| *key < separator),
+                None => keys.len(),
+            };
+            if below > 0 && !self.is_leaf {
+                self.children[index].update_sorted(&keys[..below], f);
+            }
+            keys = &keys[below..];
+
+            if index < self.keys.len() && keys.first() == Some(&&self.keys[index]) {
+                f(&self.keys[index], &mut self.values[index]);
+                keys = &keys[1..];
+            }
+            if keys.is_empty() {
+                break;
+            }
+        }
+    }
+
+    /*remove every entry whose key is in `keys` (sorted, no duplicates) into `removed`
+     * keys in this node go first, one by one while the children are still intact (a
+     * predecessor moving up may be the next one), then each child takes its share of the
+     * rest and the children left short are repaired in one pass at the end
+     */
+    pub(crate) fn remove_sorted(&mut self, keys: &[&K], removed: &mut Vec<(K, V)>) {
+        loop {
+            let before = removed.len();
+            for key in keys.iter().rev() {
+                if let Ok(pos) = self.keys.binary_search(key) {
+                    removed.push(self.remove_at(&[], pos));
+                }
+            }
+            if removed.len() == before {
+                break;
+            }
+        }
+        if self.is_leaf {
+            return;
+        }
+
+        let mut keys = keys;
+        let mut touched = false;
+        for index in 0..self.children.len() {
+            let below = match self.keys.get(index) {
+                Some(separator) => keys.partition_point(|key| *key < separator),
+                None => keys.len(),
+            };
+            if below > 0 {
+                self.children[index].remove_sorted(&keys[..below], removed);
+                touched = true;
+            }
+            keys = &keys[below..];
+        }
+        if touched {
+            self.fill_children();
+        }
+    }
+
+    /*smallest entry not below `start`, the child left of the first matching key is tried first*/
+    pub(crate) fn first_from(&self, start: Bound<&K>) -> Option<(&K, &V)> {
+        let pos = self
+            .keys
+            .iter()
+            .position(|k| match start {
+                Bound::Included(start) => k >= start,
+                Bound::Excluded(start) => k > start,
+                Bound::Unbounded => true,
+            })
+            .unwrap_or(self.keys.len());
+
+        if let Some(found) = self
+            .children
+            .get(pos)
+            .and_then(|child| child.first_from(start))
+        {
+            return Some(found);
+        }
+        (pos < self.keys.len()).then(|| (&self.keys[pos], &self.values[pos]))
+    }
+
+    /*largest entry not above `end`, mirror image of first_from*/
+    pub(crate) fn last_until(&self, end: Bound<&K>) -> Option<(&K, &V)> {
+        let pos = self
+            .keys
+            .iter()
+            .position(|k| match end {
+                Bound::Included(end) => k > end,
+                Bound::Excluded(end) => k >= end,
+                Bound::Unbounded => false,
+            })
+            .unwrap_or(self.keys.len());
+
+        if let Some(found) = self
+            .children
+            .get(pos)
+            .and_then(|child| child.last_until(end))
+        {
+            return Some(found);
+        }
+        pos.checked_sub(1)
+            .map(|index| (&self.keys[index], &self.values[index]))
+    }
+
+    /*returns the depth of the leaves below this node
+     * nodes on a relaxed spine (the right one of a time series tree, or the one a biased
+     * SplitStrategy packs towards) may be underfull, the packed edge splits leave them
+     * that way until more keys arrive, `relaxed_left` and `relaxed_right` say this node
+     * lies on such a spine
+     */
+    pub(crate) fn check_invariants(
+        &self,
+        depth: usize,
+        relaxed_left: bool,
+        relaxed_right: bool,
+        lower: Option<&K>,
+        upper: Option<&K>,
+    ) -> Result<usize, InvariantError> {
+        let broken = |problem: String| Err(InvariantError::new(problem, depth));
+        let max_keys = 2 * self.min_degree - 1;
+        if self.keys.len() != self.values.len() {
+            return broken("keys and values of different lengths".to_string());
+        }
+        if self.keys.len() > max_keys {
+            return broken(format!("holds more than {} keys", max_keys));
+        }
+        if depth > 0 && self.keys.len() < self.min_degree - 1 && !(relaxed_left || relaxed_right) {
+            return broken(format!("holds fewer than {} keys", self.min_degree - 1));
+        }
+        if self.keys.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return broken("keys out of order or repeated".to_string());
+        }
+        let first = self.keys.first();
+        let last = self.keys.last();
+        if lower.is_some_and(|lower| first.is_some_and(|first| first <= lower))
+            || upper.is_some_and(|upper| last.is_some_and(|last| last >= upper))
+        {
+            return broken("keys outside the range of their parent".to_string());
+        }
+
+        if self.is_leaf {
+            return if self.children.is_empty() {
+                Ok(depth)
+            } else {
+                broken("leaf with children".to_string())
+            };
+        }
+        if self.children.len() != self.keys.len() + 1 {
+            return broken("internal node without one more child than keys".to_string());
+        }
+
+        let mut leaf_depth = None;
+        for (index, child) in self.children.iter().enumerate() {
+            let child_lower = if index == 0 {
+                lower
+            } else {
+                self.keys.get(index - 1)
+            };
+            let child_upper = self.keys.get(index).or(upper);
+            let depth = child
+                .check_invariants(
+                    depth + 1,
+                    relaxed_left && index == 0,
+                    relaxed_right && index == self.keys.len(),
+                    child_lower,
+                    child_upper,
+                )
+                .map_err(|mut error| {
+                    error.path.insert(0, index);
+                    error
+                })?;
+            if leaf_depth.is_some_and(|leaf_depth| leaf_depth != depth) {
+                return broken("leaves at different depths below it".to_string());
+            }
+            leaf_depth = Some(depth);
+        }
+        Ok(leaf_depth.unwrap_or(depth))
+    }
+
+    /*rotate `count` keys from the child left of `index` through this node into the child at `index`*/
+    fn steal_from_left(&mut self, index: usize, count: usize) {
+        let (left, right) = self.children.split_at_mut(index);
+        let (left, right) = (&mut left[index - 1], &mut right[0]);
+
+        let split = left.keys.len() - count;
+        let mut keys: Vec<K> = left.keys.drain(split..).collect();
+        let mut values: Vec<V> = left.values.drain(split..).collect();
+        let key = std::mem::replace(&mut self.keys[index - 1], keys.remove(0));
+        let value = std::mem::replace(&mut self.values[index - 1], values.remove(0));
+
+        keys.push(key);
+        values.push(value);
+        right.keys.splice(0..0, keys);
+        right.values.splice(0..0, values);
+        if !left.is_leaf {
+            let moved: Vec<BTreeNode<K, V>> = left.children.drain(split + 1..).collect();
+            right.children.splice(0..0, moved);
+        }
+    }
+
+    pub(crate) fn collect_occupancy(&self, depth: usize, histogram: &mut Vec<Vec<usize>>) {
+        if histogram.len() == depth {
+            histogram.push(vec![0; 2 * self.min_degree]);
+        }
+        histogram[depth][self.keys.len()] += 1;
+
+        for child in &self.children {
+            child.collect_occupancy(depth + 1, histogram);
+        }
+    }
+
+    pub(crate) fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let pos = self
+            .keys
+            .iter()
+            .position(|k| k.borrow() >= key)
+            .unwrap_or(self.keys.len());
+
+        if pos < self.keys.len() && self.keys[pos].borrow() == key {
+            return Some(self.remove_at(&[], pos).1);
+        }
+
+        if self.is_leaf {
+            return None;
+        }
+        let removed = self.children[pos].remove(key);
+        if removed.is_some() {
+            self.fix_child(pos);
+        }
+        removed
+    }
+
+    /*remove the entry at `index` of the node reached by following the child indices in `path`*/
+    pub(crate) fn remove_at(&mut self, path: &[usize], index: usize) -> (K, V) {
+        if let Some((&child, path)) = path.split_first() {
+            let removed = self.children[child].remove_at(path, index);
+            self.fix_child(child);
+            return removed;
+        }
+
+        if self.is_leaf {
+            return (self.keys.remove(index), self.values.remove(index));
+        }
+
+        /*an internal key is replaced by its predecessor, the largest key of the left child*/
+        let (key, value) = self.children[index].pop_last();
+        let removed = (
+            std::mem::replace(&mut self.keys[index], key),
+            std::mem::replace(&mut self.values[index], value),
+        );
+        self.fix_child(index);
+        removed
+    }
+
+    pub(crate) fn descend(&self, path: &[usize]) -> &Self {
+        path.iter().fold(self, |node, &child| &node.children[child])
+    }
+
+    pub(crate) fn descend_mut(&mut self, path: &[usize]) -> &mut Self {
+        path.iter()
+            .fold(self, |node, &child| &mut node.children[child])
+    }
+
+    fn pop_last(&mut self) -> (K, V) {
+        if self.is_leaf {
+            return (self.keys.pop().unwrap(), self.values.pop().unwrap());
+        }
+
+        let last = self.children.len() - 1;
+        let entry = self.children[last].pop_last();
+        self.fix_child(last);
+        entry
+    }
+
+    /*a child that dropped below min_degree - 1 keys borrows one from a sibling that
+     * can spare it, otherwise it is merged with a sibling and this node loses a key
+     * (which the caller fixes in turn)
+     */
+    fn fix_child(&mut self, index: usize) {
+        let min_keys = self.min_degree - 1;
+        if self.children[index].keys.len() >= min_keys {
+            return;
+        }
+
+        let has_left = index > 0;
+        let has_right = index + 1 < self.children.len();
+        if has_left && self.children[index - 1].keys.len() > min_keys {
+            self.steal_from_left(index, 1);
+        } else if has_right && self.children[index + 1].keys.len() > min_keys {
+            self.steal_from_right(index, 1);
+        } else if has_left {
+            self.merge_children(index - 1);
+        } else if has_right {
+            self.merge_children(index);
+        }
+    }
+
+    /*fold the key at `index` and the child right of it into the child left of it*/
+    fn merge_children(&mut self, index: usize) {
+        let right = self.children.remove(index + 1);
+        let key = self.keys.remove(index);
+        let value = self.values.remove(index);
+
+        let left = &mut self.children[index];
+        left.keys.push(key);
+        left.values.push(value);
+        left.keys.extend(right.keys);
+        left.values.extend(right.values);
+        left.children.extend(right.children);
+    }
+
+    /*rotate `count` keys from the child right of `index` through this node into the child at `index`*/
+    fn steal_from_right(&mut self, index: usize, count: usize) {
+        let (left, right) = self.children.split_at_mut(index + 1);
+        let (left, right) = (&mut left[index], &mut right[0]);
+
+        let mut keys: Vec<K> = right.keys.drain(..count).collect();
+        let mut values: Vec<V> = right.values.drain(..count).collect();
+        let key = std::mem::replace(&mut self.keys[index], keys.pop().unwrap());
+        let value = std::mem::replace(&mut self.values[index], values.pop().unwrap());
+
+        left.keys.push(key);
+        left.values.push(value);
+        left.keys.extend(keys);
+        left.values.extend(values);
+        if !right.is_leaf {
+            left.children.extend(right.children.drain(..count));
+        }
+    }
+}
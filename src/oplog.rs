@@ -0,0 +1,127 @@
+use crate::{BTreeMap, Codec};
+use std::io::{self, Read, Write};
+
+/*exported oplogs start with the magic and the format version, see the README for the layout*/
+const OPLOG_MAGIC: &[u8; 4] = b"BTOL";
+const OPLOG_VERSION: u8 = 1;
+
+/*one byte tag per recorded operation, followed by its arguments*/
+const OP_INSERT: u8 = 0;
+const OP_MERGE: u8 = 1;
+const OP_TRIM_BEFORE: u8 = 2;
+const OP_REMOVE: u8 = 3;
+
+/*wraps a map and logs every mutation to `log` before applying it
+ * reads go through map(), the log can be handed to BTreeMap::replay later
+ */
+#[derive(Debug)]
+pub struct Recorder<K, V, W> {
+    map: BTreeMap<K, V>,
+    log: W,
+}
+
+impl<K: Ord + Codec, V: Codec, W: Write> Recorder<K, V, W> {
+    pub fn new(map: BTreeMap<K, V>, log: W) -> Self {
+        Recorder { map, log }
+    }
+
+    pub fn map(&self) -> &BTreeMap<K, V> {
+        &self.map
+    }
+
+    pub fn into_parts(self) -> (BTreeMap<K, V>, W) {
+        (self.map, self.log)
+    }
+
+    pub fn insert(&mut self, key: K, value: V) -> io::Result<Option<V>> {
+        OP_INSERT.encode(&mut self.log)?;
+        key.encode(&mut self.log)?;
+        value.encode(&mut self.log)?;
+        Ok(self.map.insert(key, value))
+    }
+
+    pub fn merge(&mut self, key: K, operand: V) -> io::Result<()> {
+        OP_MERGE.encode(&mut self.log)?;
+        key.encode(&mut self.log)?;
+        operand.encode(&mut self.log)?;
+        self.map.merge(key, operand);
+        Ok(())
+    }
+
+    pub fn trim_before(&mut self, key: &K) -> io::Result<()> {
+        OP_TRIM_BEFORE.encode(&mut self.log)?;
+        key.encode(&mut self.log)?;
+        self.map.trim_before(key);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, key: &K) -> io::Result<Option<V>> {
+        OP_REMOVE.encode(&mut self.log)?;
+        key.encode(&mut self.log)?;
+        Ok(self.map.remove(key))
+    }
+}
+
+impl<K: Ord + Codec, V: Codec> Recorder<K, V, Vec<u8>> {
+    /*hand out everything recorded since the last export as a self describing oplog
+     * shipping these to another process and applying them in order keeps both maps in sync
+     */
+    pub fn export_oplog(&mut self) -> Vec<u8> {
+        let mut oplog = Vec::with_capacity(OPLOG_MAGIC.len() + 1 + self.log.len());
+        oplog.extend_from_slice(OPLOG_MAGIC);
+        oplog.push(OPLOG_VERSION);
+        oplog.append(&mut self.log);
+        oplog
+    }
+}
+
+impl<K: Ord + Codec, V: Codec> BTreeMap<K, V> {
+    /*apply an oplog produced by export_oplog*/
+    pub fn apply_oplog<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
+        let mut header = [0; 5];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != OPLOG_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an oplog"));
+        }
+        if header[4] != OPLOG_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported oplog version {}", header[4]),
+            ));
+        }
+        self.replay(reader)
+    }
+
+    /*apply a log written by Recorder, stops cleanly at the end of the input
+     * a map with the same merge operator has to be used if the log contains merges
+     */
+    pub fn replay<R: Read>(&mut self, mut reader: R) -> io::Result<()> {
+        loop {
+            let mut op = [0];
+            if reader.read(&mut op)? == 0 {
+                return Ok(());
+            }
+
+            match op[0] {
+                OP_INSERT => {
+                    let key = K::decode(&mut reader)?;
+                    self.insert(key, V::decode(&mut reader)?);
+                }
+                OP_MERGE => {
+                    let key = K::decode(&mut reader)?;
+                    self.merge(key, V::decode(&mut reader)?);
+                }
+                OP_TRIM_BEFORE => self.trim_before(&K::decode(&mut reader)?),
+                OP_REMOVE => {
+                    self.remove(&K::decode(&mut reader)?);
+                }
+                op => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unknown operation {}", op),
+                    ))
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,163 @@
+use crate::disk::PAGE_SIZE;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/*where DiskBTreeMap keeps its pages, implement it to put them on object storage or a block
+ * device, every page is PAGE_SIZE bytes and pages are numbered from 0 (the header)
+ * writes past the end grow the store, new pages are not written in id order
+ */
+pub trait PageStore {
+    fn read_page(&mut self, id: u64, page: &mut [u8]) -> io::Result<()>;
+    fn write_page(&mut self, id: u64, page: &[u8]) -> io::Result<()>;
+    /*0 for a new store, the map then writes a fresh header*/
+    fn page_count(&mut self) -> io::Result<u64>;
+    /*make every write so far durable, flush and close wait for it*/
+    fn sync(&mut self) -> io::Result<()>;
+}
+
+/*pages in a file at their offset, what DiskBTreeMap::open uses*/
+#[derive(Debug)]
+pub struct FilePages {
+    file: File,
+}
+
+impl FilePages {
+    /*an empty file is created if there is none*/
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)?;
+        Ok(FilePages { file })
+    }
+}
+
+impl PageStore for FilePages {
+    fn read_page(&mut self, id: u64, page: &mut [u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(id * PAGE_SIZE as u64))?;
+        self.file.read_exact(page)
+    }
+
+    fn write_page(&mut self, id: u64, page: &[u8]) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(id * PAGE_SIZE as u64))?;
+        self.file.write_all(page)
+    }
+
+    fn page_count(&mut self) -> io::Result<u64> {
+        /*a torn last page still counts, so the header check catches a short file*/
+        Ok(self.file.metadata()?.len().div_ceil(PAGE_SIZE as u64))
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        self.file.sync_data()
+    }
+}
+
+/*pages in memory, for tests and throwaway indexes, clones share the same pages, so a
+ * clone kept aside can open the map again after it was closed
+ */
+#[derive(Debug, Clone, Default)]
+pub struct MemoryPages {
+    bytes: Arc<Mutex<Vec<u8>>>,
+}
+
+impl MemoryPages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PageStore for MemoryPages {
+    fn read_page(&mut self, id: u64, page: &mut [u8]) -> io::Result<()> {
+        let bytes = self.bytes.lock().unwrap();
+        let start = id as usize * PAGE_SIZE;
+        let stored = bytes.get(start..start + page.len()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "page past the end of the store",
+            )
+        })?;
+        page.copy_from_slice(stored);
+        Ok(())
+    }
+
+    fn write_page(&mut self, id: u64, page: &[u8]) -> io::Result<()> {
+        let mut bytes = self.bytes.lock().unwrap();
+        let start = id as usize * PAGE_SIZE;
+        if bytes.len() < start + page.len() {
+            bytes.resize(start + page.len(), 0);
+        }
+        bytes[start..start + page.len()].copy_from_slice(page);
+        Ok(())
+    }
+
+    fn page_count(&mut self) -> io::Result<u64> {
+        Ok(self.bytes.lock().unwrap().len().div_ceil(PAGE_SIZE) as u64)
+    }
+
+    fn sync(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(fill: u8) -> Vec<u8> {
+        (0..PAGE_SIZE).map(|i| fill ^ i as u8).collect()
+    }
+
+    /*what DiskBTreeMap relies on from every store, `reopen` opens the same pages again*/
+    fn check_contract<S: PageStore>(mut store: S, reopen: impl FnOnce() -> S) {
+        let mut read = vec![0; PAGE_SIZE];
+        assert_eq!(store.page_count().unwrap(), 0);
+        let error = store.read_page(0, &mut read).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        store.write_page(0, &page(1)).unwrap();
+        assert_eq!(store.page_count().unwrap(), 1);
+
+        /*out of order, the pages skipped read as zeros*/
+        store.write_page(3, &page(3)).unwrap();
+        assert_eq!(store.page_count().unwrap(), 4);
+        store.read_page(2, &mut read).unwrap();
+        assert!(read.iter().all(|&byte| byte == 0));
+        store.write_page(2, &page(2)).unwrap();
+        store.write_page(0, &page(4)).unwrap();
+        assert_eq!(store.page_count().unwrap(), 4);
+        let error = store.read_page(4, &mut read).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+        store.sync().unwrap();
+        drop(store);
+
+        let mut store = reopen();
+        assert_eq!(store.page_count().unwrap(), 4);
+        for (id, fill) in [(0, 4), (2, 2), (3, 3)] {
+            store.read_page(id, &mut read).unwrap();
+            assert_eq!(read, page(fill), "page {id}");
+        }
+        store.read_page(1, &mut read).unwrap();
+        assert!(read.iter().all(|&byte| byte == 0));
+    }
+
+    #[test]
+    fn file_pages_keep_the_contract() {
+        let path = std::env::temp_dir().join(format!("btreemap-pages-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        check_contract(FilePages::open(&path).unwrap(), || {
+            FilePages::open(&path).unwrap()
+        });
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn memory_pages_keep_the_contract() {
+        let pages = MemoryPages::new();
+        check_contract(pages.clone(), || pages);
+    }
+}
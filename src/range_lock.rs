@@ -0,0 +1,101 @@
+use std::ops::{Bound, RangeBounds};
+use std::sync::{Condvar, Mutex};
+
+/*advisory locks over key ranges for callers doing multi step read-modify-write on a window
+ * of keys, kept next to the map (not inside it) so waiting for a range never holds
+ * whatever lock guards the map itself
+ */
+#[derive(Debug)]
+pub struct RangeLocks<K> {
+    held: Mutex<HeldRanges<K>>,
+    released: Condvar,
+}
+
+#[derive(Debug)]
+struct HeldRanges<K> {
+    next_id: u64,
+    ranges: Vec<(u64, Bound<K>, Bound<K>)>,
+}
+
+/*releases its range when dropped*/
+#[derive(Debug)]
+pub struct RangeGuard<'a, K> {
+    locks: &'a RangeLocks<K>,
+    id: u64,
+}
+
+impl<K: Ord + Clone> RangeLocks<K> {
+    pub const fn new() -> Self {
+        RangeLocks {
+            held: Mutex::new(HeldRanges {
+                next_id: 0,
+                ranges: Vec::new(),
+            }),
+            released: Condvar::new(),
+        }
+    }
+
+    /*blocks until no overlapping range is held*/
+    pub fn lock_range<R: RangeBounds<K>>(&self, range: R) -> RangeGuard<'_, K> {
+        let (start, end) = (range.start_bound().cloned(), range.end_bound().cloned());
+        let mut held = self.held.lock().unwrap();
+        while Self::conflicts(&held.ranges, &start, &end) {
+            held = self.released.wait(held).unwrap();
+        }
+        self.acquire(&mut held, start, end)
+    }
+
+    pub fn try_lock_range<R: RangeBounds<K>>(&self, range: R) -> Option<RangeGuard<'_, K>> {
+        let (start, end) = (range.start_bound().cloned(), range.end_bound().cloned());
+        let mut held = self.held.lock().unwrap();
+        if Self::conflicts(&held.ranges, &start, &end) {
+            return None;
+        }
+        Some(self.acquire(&mut held, start, end))
+    }
+
+    fn acquire(
+        &self,
+        held: &mut HeldRanges<K>,
+        start: Bound<K>,
+        end: Bound<K>,
+    ) -> RangeGuard<'_, K> {
+        held.next_id += 1;
+        held.ranges.push((held.next_id, start, end));
+        RangeGuard {
+            locks: self,
+            id: held.next_id,
+        }
+    }
+
+    fn conflicts(held: &[(u64, Bound<K>, Bound<K>)], start: &Bound<K>, end: &Bound<K>) -> bool {
+        held.iter().any(|(_, held_start, held_end)| {
+            !Self::ends_before(end, held_start) && !Self::ends_before(held_end, start)
+        })
+    }
+
+    /*whether a range ending at `end` lies entirely before one starting at `start`*/
+    fn ends_before(end: &Bound<K>, start: &Bound<K>) -> bool {
+        match (end, start) {
+            (Bound::Unbounded, _) | (_, Bound::Unbounded) => false,
+            (Bound::Included(end), Bound::Included(start)) => end < start,
+            (Bound::Included(end), Bound::Excluded(start))
+            | (Bound::Excluded(end), Bound::Included(start))
+            | (Bound::Excluded(end), Bound::Excluded(start)) => end <= start,
+        }
+    }
+}
+
+impl<K: Ord + Clone> Default for RangeLocks<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K> Drop for RangeGuard<'_, K> {
+    fn drop(&mut self) {
+        let mut held = self.locks.held.lock().unwrap();
+        held.ranges.retain(|(id, _, _)| *id != self.id);
+        self.locks.released.notify_all();
+    }
+}
@@ -0,0 +1,181 @@
+use crate::BTreeMap;
+use std::borrow::Borrow;
+
+/*map with an undo window for deletions: remove only marks an entry deleted, reads and
+ * iterators skip it until restore brings it back or purge drops it for good
+ * an insert over a deleted entry replaces it and makes it live again
+ */
+#[derive(Debug)]
+pub struct SoftDeleteMap<K, V> {
+    map: BTreeMap<K, (V, bool)>,
+    deleted: usize,
+}
+
+impl<K: Ord, V> SoftDeleteMap<K, V> {
+    pub fn new(min_degree: usize) -> Self {
+        SoftDeleteMap {
+            map: BTreeMap::new(min_degree),
+            deleted: 0,
+        }
+    }
+
+    /*returns the old value if the key was live, a deleted one is dropped silently*/
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.map.insert(key, (value, false)) {
+            Some((old, false)) => Some(old),
+            Some((_, true)) => {
+                self.deleted -= 1;
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.map.get(key) {
+            Some((value, false)) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    /*hide the entry, false if there was no live entry for `key`*/
+    pub fn remove<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.map.get_mut(key) {
+            Some((_, deleted)) if !*deleted => {
+                *deleted = true;
+                self.deleted += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /*undo a remove, false if `key` isn't a deleted entry*/
+    pub fn restore<Q>(&mut self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        match self.map.get_mut(key) {
+            Some((_, deleted)) if *deleted => {
+                *deleted = false;
+                self.deleted -= 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /*drop every deleted entry, ending their undo window, and rebuild the tree from the
+     * live ones like WeakValueMap::prune, returns how many were dropped
+     */
+    pub fn purge(&mut self) -> usize {
+        let purged = self.deleted;
+        if purged > 0 {
+            let min_degree = self.map.min_degree();
+            let live = std::mem::replace(&mut self.map, BTreeMap::new(min_degree))
+                .into_iter()
+                .filter(|(_, (_, deleted))| !deleted)
+                .collect();
+            self.map = BTreeMap::from_sorted_vec(min_degree, live);
+            self.deleted = 0;
+        }
+        purged
+    }
+
+    /*live entries only*/
+    pub fn len(&self) -> usize {
+        self.map.len() - self.deleted
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /*entries waiting in the undo window*/
+    pub fn deleted_len(&self) -> usize {
+        self.deleted
+    }
+
+    /*live entries in key order*/
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map
+            .iter()
+            .filter(|(_, (_, deleted))| !deleted)
+            .map(|(key, (value, _))| (key, value))
+    }
+
+    /*deleted entries in key order, e.g. to show what restore could bring back*/
+    pub fn deleted(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.map
+            .iter()
+            .filter(|(_, (_, deleted))| *deleted)
+            .map(|(key, (value, _))| (key, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_brings_the_value_back() {
+        let mut map = SoftDeleteMap::new(2);
+        for key in 0..20 {
+            map.insert(key, key * 10);
+        }
+
+        assert!(map.remove(&7));
+        assert!(!map.remove(&7));
+        assert_eq!(map.get(&7), None);
+        assert!(!map.contains_key(&7));
+        assert_eq!((map.len(), map.deleted_len()), (19, 1));
+        assert_eq!(map.deleted().collect::<Vec<_>>(), vec![(&7, &70)]);
+
+        assert!(map.restore(&7));
+        assert!(!map.restore(&7));
+        assert_eq!(map.get(&7), Some(&70));
+        assert_eq!((map.len(), map.deleted_len()), (20, 0));
+    }
+
+    #[test]
+    fn purge_drops_deleted_entries_for_good() {
+        let mut map = SoftDeleteMap::new(2);
+        for key in 0..20 {
+            map.insert(key, key * 10);
+        }
+        for key in (0..20).step_by(3) {
+            assert!(map.remove(&key));
+        }
+
+        assert_eq!(map.purge(), 7);
+        assert_eq!(map.purge(), 0);
+        assert_eq!(map.deleted_len(), 0);
+        assert_eq!(map.len(), 13);
+        for key in 0..20 {
+            assert!(!map.restore(&key));
+            let expected = (key % 3 != 0).then_some(key * 10);
+            assert_eq!(map.get(&key).copied(), expected);
+        }
+        assert!(map.iter().all(|(key, _)| key % 3 != 0));
+
+        assert_eq!(map.insert(3, 1), None);
+        assert_eq!(map.get(&3), Some(&1));
+    }
+}
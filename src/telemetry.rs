@@ -0,0 +1,135 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/*HDR style histogram: bucket n counts operations that took [2^n, 2^(n+1)) nanoseconds*/
+#[derive(Debug, Clone)]
+pub struct LatencyHistogram {
+    buckets: [u64; 64],
+}
+
+impl LatencyHistogram {
+    pub fn count(&self) -> u64 {
+        self.buckets.iter().sum()
+    }
+
+    /*upper edge of the bucket holding the q-th quantile, e.g. percentile(0.99) for p99*/
+    pub fn percentile(&self, q: f64) -> std::time::Duration {
+        let target = (self.count() as f64 * q).ceil() as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target.max(1) {
+                let edge = 1u64.checked_shl(bucket as u32 + 1).unwrap_or(u64::MAX);
+                return std::time::Duration::from_nanos(edge);
+            }
+        }
+        std::time::Duration::ZERO
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Metrics {
+    pub search: LatencyHistogram,
+    pub insert: LatencyHistogram,
+    pub remove: LatencyHistogram,
+    pub range: LatencyHistogram,
+}
+
+/*the counters behind a LatencyHistogram, plain atomics so lookups through &self can
+ * record from several threads at once and the map stays Sync
+ */
+#[derive(Debug)]
+struct AtomicHistogram {
+    buckets: [AtomicU64; 64],
+}
+
+impl AtomicHistogram {
+    const fn new() -> Self {
+        AtomicHistogram {
+            buckets: [const { AtomicU64::new(0) }; 64],
+        }
+    }
+
+    fn record(&self, latency: std::time::Duration) {
+        let nanos = u64::try_from(latency.as_nanos()).unwrap_or(u64::MAX).max(1);
+        self.buckets[nanos.ilog2() as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LatencyHistogram {
+        LatencyHistogram {
+            buckets: std::array::from_fn(|n| self.buckets[n].load(Ordering::Relaxed)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct MetricsCell {
+    search: AtomicHistogram,
+    insert: AtomicHistogram,
+    remove: AtomicHistogram,
+    range: AtomicHistogram,
+}
+
+impl MetricsCell {
+    pub(crate) const fn new() -> Self {
+        MetricsCell {
+            search: AtomicHistogram::new(),
+            insert: AtomicHistogram::new(),
+            remove: AtomicHistogram::new(),
+            range: AtomicHistogram::new(),
+        }
+    }
+
+    pub(crate) fn record_search(&self, latency: std::time::Duration) {
+        self.search.record(latency);
+    }
+
+    pub(crate) fn record_insert(&self, latency: std::time::Duration) {
+        self.insert.record(latency);
+    }
+
+    pub(crate) fn record_remove(&self, latency: std::time::Duration) {
+        self.remove.record(latency);
+    }
+
+    pub(crate) fn record_range(&self, latency: std::time::Duration) {
+        self.range.record(latency);
+    }
+
+    /*the counts are read one by one, a snapshot taken during other operations may be
+     * off by the few that were in flight
+     */
+    pub(crate) fn snapshot(&self) -> Metrics {
+        Metrics {
+            search: self.search.snapshot(),
+            insert: self.insert.snapshot(),
+            remove: self.remove.snapshot(),
+            range: self.range.snapshot(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn percentile_is_the_upper_edge_of_the_bucket() {
+        let histogram = AtomicHistogram::new();
+        histogram.record(Duration::from_nanos(1));
+        histogram.record(Duration::from_nanos(700));
+        histogram.record(Duration::MAX);
+        let snapshot = histogram.snapshot();
+
+        assert_eq!(snapshot.count(), 3);
+        assert_eq!(snapshot.percentile(0.0), Duration::from_nanos(2));
+        assert_eq!(snapshot.percentile(0.5), Duration::from_nanos(1024));
+        assert_eq!(snapshot.percentile(1.0), Duration::from_nanos(u64::MAX));
+    }
+
+    #[test]
+    fn maps_with_telemetry_are_sync() {
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<crate::BTreeMap<u32, String>>();
+    }
+}
@@ -0,0 +1,160 @@
+use crate::BTreeMap;
+use std::ops::RangeBounds;
+
+/*insert_with_version was given u64::MAX, which is kept free so the counter always has a
+ * next version to hand out
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VersionOverflow;
+
+impl std::fmt::Display for VersionOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "version u64::MAX leaves no version for the next write")
+    }
+}
+
+impl std::error::Error for VersionOverflow {}
+
+/*hand out the counter's version and move it on, a free function so callers can hold
+ * an entry of the map meanwhile
+ */
+fn take_version(next_version: &mut u64) -> u64 {
+    let version = *next_version;
+    assert!(version < u64::MAX, "VersionedBTreeMap ran out of versions");
+    *next_version = version + 1;
+    version
+}
+
+/*every entry carries the version it was last written at
+ * insert stamps the next value of a monotonically increasing counter,
+ * insert_with_version lets the caller supply its own (e.g. a timestamp)
+ * the version can then be used for optimistic updates or to resolve
+ * conflicts between replicas (highest version wins), replication layers can keep an LSN
+ * or an origin id in it, every read and iterator hands it out next to the value
+ */
+#[derive(Debug)]
+pub struct VersionedBTreeMap<K, V> {
+    map: BTreeMap<K, (V, u64)>,
+    next_version: u64,
+}
+
+impl<K: Ord, V> VersionedBTreeMap<K, V> {
+    pub fn new(min_degree: usize) -> Self {
+        VersionedBTreeMap {
+            map: BTreeMap::new(min_degree),
+            next_version: 1,
+        }
+    }
+
+    /*panics once the versions run out, i.e. when the next one would be u64::MAX*/
+    pub fn insert(&mut self, key: K, value: V) -> u64 {
+        let version = take_version(&mut self.next_version);
+        self.map.insert(key, (value, version));
+        version
+    }
+
+    /*user supplied versions keep the counter ahead of anything seen so far
+     * u64::MAX is refused, the counter would have nowhere to go after it
+     */
+    pub fn insert_with_version(
+        &mut self,
+        key: K,
+        value: V,
+        version: u64,
+    ) -> Result<(), VersionOverflow> {
+        let next = version.checked_add(1).ok_or(VersionOverflow)?;
+        self.next_version = self.next_version.max(next);
+        self.map.insert(key, (value, version));
+        Ok(())
+    }
+
+    pub fn get_versioned(&self, key: &K) -> Option<(&V, u64)> {
+        self.map.get(key).map(|(value, version)| (value, *version))
+    }
+
+    /*optimistic update: the write only happens if nobody else wrote the key
+     * since `expected` was read, otherwise the current version is returned
+     * a write panics like insert once the versions run out
+     */
+    pub fn compare_and_set(
+        &mut self,
+        key: &K,
+        expected: u64,
+        value: V,
+    ) -> Result<u64, Option<u64>> {
+        match self.map.get_mut(key) {
+            Some(entry) if entry.1 == expected => {
+                let version = take_version(&mut self.next_version);
+                *entry = (value, version);
+                Ok(version)
+            }
+            Some(entry) => Err(Some(entry.1)),
+            None => Err(None),
+        }
+    }
+
+    pub fn remove(&mut self, key: &K) -> Option<(V, u64)> {
+        self.map.remove(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /*entries with their versions in key order*/
+    pub fn iter_versioned(&self) -> impl Iterator<Item = (&K, &V, u64)> {
+        self.map
+            .iter()
+            .map(|(key, (value, version))| (key, value, *version))
+    }
+
+    /*entries written after `version`, e.g. what a replica that has seen up to it is missing
+     * nothing is indexed by version, so this scans the whole map
+     */
+    pub fn changed_since(&self, version: u64) -> impl Iterator<Item = (&K, &V, u64)> {
+        self.iter_versioned()
+            .filter(move |(_, _, written)| *written > version)
+    }
+}
+
+impl<K: Ord + Clone, V> VersionedBTreeMap<K, V> {
+    pub fn range_versioned<R: RangeBounds<K>>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = (&K, &V, u64)> {
+        self.map
+            .range(range)
+            .map(|(key, (value, version))| (key, value, *version))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_at_the_top_of_u64() {
+        let mut map = VersionedBTreeMap::new(2);
+        assert_eq!(
+            map.insert_with_version("a", 1, u64::MAX),
+            Err(VersionOverflow)
+        );
+        assert!(map.is_empty());
+
+        map.insert_with_version("a", 1, u64::MAX - 2).unwrap();
+        assert_eq!(map.insert("b", 2), u64::MAX - 1);
+        assert_eq!(map.get_versioned(&"b"), Some((&2, u64::MAX - 1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "ran out of versions")]
+    fn write_past_the_last_version_panics() {
+        let mut map = VersionedBTreeMap::new(2);
+        map.insert_with_version("a", 1, u64::MAX - 1).unwrap();
+        let _ = map.compare_and_set(&"a", u64::MAX - 1, 2);
+    }
+}
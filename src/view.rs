@@ -0,0 +1,83 @@
+use crate::{BTreeMap, Range};
+use std::borrow::Borrow;
+use std::ops::Bound;
+
+/*what range_stats found in a range, bytes as in estimate_range_size*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeStats<'a, K> {
+    pub entries: usize,
+    pub bytes: usize,
+    pub min_key: Option<&'a K>,
+    pub max_key: Option<&'a K>,
+}
+
+/*whether `key` lies between the stored bounds of a view or an editor
+ * RangeBounds::contains can't compare a K bound against a borrowed Q key
+ */
+pub(crate) fn in_range<K, Q>(start: &Bound<K>, end: &Bound<K>, key: &Q) -> bool
+where
+    K: Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    let after_start = match start {
+        Bound::Included(start) => key >= start.borrow(),
+        Bound::Excluded(start) => key > start.borrow(),
+        Bound::Unbounded => true,
+    };
+    let before_end = match end {
+        Bound::Included(end) => key <= end.borrow(),
+        Bound::Excluded(end) => key < end.borrow(),
+        Bound::Unbounded => true,
+    };
+    after_start && before_end
+}
+
+/*read only window onto the keys of a map inside a range
+ * it borrows the map, nothing is copied, and keys outside the range can't be reached
+ * through it
+ */
+#[derive(Debug)]
+pub struct MapView<'a, K, V> {
+    map: &'a BTreeMap<K, V>,
+    start: Bound<K>,
+    end: Bound<K>,
+}
+
+impl<'a, K: Ord + Clone, V> MapView<'a, K, V> {
+    pub(crate) fn new(map: &'a BTreeMap<K, V>, start: Bound<K>, end: Bound<K>) -> Self {
+        MapView { map, start, end }
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<&'a V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        if in_range(&self.start, &self.end, key) {
+            self.map.get(key)
+        } else {
+            None
+        }
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self.get(key).is_some()
+    }
+
+    pub fn iter(&self) -> Range<'a, K, V> {
+        self.map.range((self.start.clone(), self.end.clone()))
+    }
+
+    /*nodes keep no subtree counts, so this walks the range*/
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
@@ -0,0 +1,70 @@
+use crate::node::BTreeNode;
+use crate::BTreeMap;
+
+/*what a visitor gets to know about a node, the root is at depth 0*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeInfo {
+    pub depth: usize,
+    pub len: usize,
+    pub is_leaf: bool,
+    pub min_degree: usize,
+}
+
+/*callbacks for BTreeMap::visit, every one defaults to doing nothing
+ * nodes are walked depth first and entries come in key order, so between enter_node and
+ * leave_node of a node come the subtree before its first key, the first key, the subtree
+ * after it and so on
+ */
+pub trait TreeVisitor<K, V> {
+    fn enter_node(&mut self, _node: NodeInfo) {}
+
+    fn visit_entry(&mut self, _depth: usize, _key: &K, _value: &V) {}
+
+    fn leave_node(&mut self, _node: NodeInfo) {}
+}
+
+/*so a visitor can be lent to visit and its results read afterwards*/
+impl<K, V, T: TreeVisitor<K, V> + ?Sized> TreeVisitor<K, V> for &mut T {
+    fn enter_node(&mut self, node: NodeInfo) {
+        (**self).enter_node(node)
+    }
+
+    fn visit_entry(&mut self, depth: usize, key: &K, value: &V) {
+        (**self).visit_entry(depth, key, value)
+    }
+
+    fn leave_node(&mut self, node: NodeInfo) {
+        (**self).leave_node(node)
+    }
+}
+
+impl<K: Ord, V> BTreeMap<K, V> {
+    /*walk the whole tree with `visitor`, for stats, exporters or checks the crate doesn't
+     * ship, without access to the nodes themselves
+     */
+    pub fn visit(&self, mut visitor: impl TreeVisitor<K, V>) {
+        if let Some(root) = &self.root {
+            visit_node(root, 0, &mut visitor);
+        }
+    }
+}
+
+fn visit_node<K, V>(node: &BTreeNode<K, V>, depth: usize, visitor: &mut impl TreeVisitor<K, V>) {
+    let info = NodeInfo {
+        depth,
+        len: node.keys.len(),
+        is_leaf: node.is_leaf,
+        min_degree: node.min_degree,
+    };
+    visitor.enter_node(info);
+    for (i, (key, value)) in node.keys.iter().zip(&node.values).enumerate() {
+        if let Some(child) = node.children.get(i) {
+            visit_node(child, depth + 1, visitor);
+        }
+        visitor.visit_entry(depth, key, value);
+    }
+    if let Some(child) = node.children.get(node.keys.len()) {
+        visit_node(child, depth + 1, visitor);
+    }
+    visitor.leave_node(info);
+}
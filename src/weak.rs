@@ -0,0 +1,45 @@
+use crate::BTreeMap;
+use std::sync::{Arc, Weak};
+
+/*registry that doesn't keep its values alive
+ * entries whose value was dropped elsewhere read as missing until prune() clears them out
+ */
+#[derive(Debug)]
+pub struct WeakValueMap<K, T> {
+    map: BTreeMap<K, Weak<T>>,
+}
+
+impl<K: Ord, T> WeakValueMap<K, T> {
+    pub fn new(min_degree: usize) -> Self {
+        WeakValueMap {
+            map: BTreeMap::new(min_degree),
+        }
+    }
+
+    pub fn insert(&mut self, key: K, value: &Arc<T>) {
+        self.map.insert(key, Arc::downgrade(value));
+    }
+
+    pub fn get_strong(&self, key: &K) -> Option<Arc<T>> {
+        self.map.get(key).and_then(Weak::upgrade)
+    }
+
+    /*rebuild the tree from the live entries, returns how many dead ones were dropped*/
+    pub fn prune(&mut self) -> usize {
+        let dead = self
+            .map
+            .values()
+            .filter(|value| value.strong_count() == 0)
+            .count();
+
+        if dead > 0 {
+            let min_degree = self.map.min_degree();
+            let live = std::mem::replace(&mut self.map, BTreeMap::new(min_degree))
+                .into_iter()
+                .filter(|(_, value)| value.strong_count() > 0)
+                .collect();
+            self.map = BTreeMap::from_sorted_vec(min_degree, live);
+        }
+        dead
+    }
+}